@@ -1,5 +1,6 @@
-use crate::invocable::runner;
+use crate::invocable::{packs, runner};
 use tauri_plugin_cli::Matches;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::sync::Mutex;
 use serde_json::Value;
@@ -30,13 +31,49 @@ pub async fn handle_cli_execution(app: tauri::AppHandle, matches: Matches) -> Op
         return Some(script_content);
     }
 
+    let options = runner::ScriptOptions {
+        dry_run: matches.args.get("dry-run").and_then(|d| d.value.as_bool()).unwrap_or(false),
+        script_path: matches.args.get("file").and_then(|f| f.value.as_str()).map(PathBuf::from),
+        ..Default::default()
+    };
+
     // Execute the script
-    handle_script_exec(app, script_content).await
+    handle_script_exec(app, script_content, options).await
+}
+
+pub async fn handle_pack_install(app: tauri::AppHandle, matches: Matches) {
+    let path = matches.args.get("pack").and_then(|p| p.value.as_str()).unwrap_or_default();
+    match packs::install_mod_pack(&app, Path::new(path), None, None).await {
+        Ok(install) => {
+            println!("Installed mod pack '{}' for {}: {}", install.name, install.game_id, install.installed.join(", "));
+            if install.repo_commit.is_some() && install.repo_commit != install.local_commit {
+                println!("Note: the pack was made at repo commit {}, mudfiles from the pack were used", install.repo_commit.unwrap_or_default());
+            }
+            process::exit(0);
+        },
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-async fn handle_script_exec(app: tauri::AppHandle, script_content: String) -> Option<String> {
-    match runner::exec_script(app, script_content, None).await {
-        Ok(_output) => {
+async fn handle_script_exec(
+    app: tauri::AppHandle,
+    script_content: String,
+    options: runner::ScriptOptions,
+) -> Option<String> {
+    let dry_run = options.dry_run;
+    match runner::exec_script(app, script_content, None, options).await {
+        Ok(output) => {
+            if dry_run {
+                // Print the plan alone so it can be piped elsewhere
+                let plan = serde_json::from_str::<Value>(&output)
+                    .ok()
+                    .and_then(|parsed| parsed.get("plan").cloned())
+                    .unwrap_or(Value::Null);
+                println!("{}", serde_json::to_string_pretty(&plan).unwrap_or_default());
+            }
             process::exit(0);
         },
         Err(e) => {
@@ -0,0 +1,197 @@
+use crate::commands::handles::put_array;
+use crate::utils::archive::{
+    extract_archive, extract_targets, list_archive, ArchiveFormat, ExtractOptions,
+};
+use crate::utils::files::absolute_path;
+use duckscript::types::command::{Command, CommandArgs, CommandResult, Commands};
+use duckscript::types::error::ScriptError;
+use std::path::{Path, PathBuf};
+
+// Options of `extract` that take a value
+static VALUE_OPTIONS: &[&str] = &["--path", "--include", "--exclude", "--format"];
+
+/*
+Arguments of `extract`, parsed so the wrappers in tracked/staged/recording
+can find the archive and destination without mistaking option values for them.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct ExtractArgs {
+    // Indexes into the argument list
+    pub archive: Option<usize>,
+    pub destination: Option<usize>,
+    pub sub_path: Option<String>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub format: Option<String>,
+}
+
+impl ExtractArgs {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut positional = Vec::new();
+        let mut index = 0;
+        while index < args.len() {
+            let arg = &args[index];
+            if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = args
+                    .get(index + 1)
+                    .cloned()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                match arg.as_str() {
+                    "--path" => parsed.sub_path = Some(value),
+                    "--include" => parsed.include.push(value),
+                    "--exclude" => parsed.exclude.push(value),
+                    _ => parsed.format = Some(value),
+                }
+                index += 2;
+                continue;
+            }
+            positional.push(index);
+            index += 1;
+        }
+        parsed.archive = positional.first().copied();
+        parsed.destination = positional.get(1).copied();
+        Ok(parsed)
+    }
+
+    // The files `extract` would write, read from the archive's listing
+    pub fn targets(&self, args: &[String]) -> Result<Vec<PathBuf>, String> {
+        let (archive, destination) = match (self.archive, self.destination) {
+            (Some(archive), Some(destination)) => {
+                (resolve(&args[archive]), resolve(&args[destination]))
+            }
+            _ => return Err("Archive and destination paths are required".to_string()),
+        };
+        let format = archive_format(&archive, self.format.as_deref())?;
+        let options = ExtractOptions::new(self.sub_path.clone(), &self.include, &self.exclude)?;
+        extract_targets(&archive, &destination, format, &options)
+            .map_err(|e| format!("Failed to read {:?}: {}", archive, e))
+    }
+}
+
+fn resolve(arg: &str) -> PathBuf {
+    absolute_path(Path::new(arg)).unwrap_or_else(|_| PathBuf::from(arg))
+}
+
+fn archive_format(archive: &Path, format: Option<&str>) -> Result<ArchiveFormat, String> {
+    match format {
+        Some(format) => ArchiveFormat::from_name(format)
+            .ok_or_else(|| format!("Unknown archive format '{}'", format)),
+        None => ArchiveFormat::detect(archive).ok_or_else(|| {
+            format!(
+                "Can't tell the format of {:?}, pass --format zip|tar|tar.gz|tar.xz|7z",
+                archive
+            )
+        }),
+    }
+}
+
+#[derive(Clone)]
+pub struct ExtractCommand;
+
+impl Command for ExtractCommand {
+    fn name(&self) -> String {
+        "extract".to_string()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        vec!["unarchive".to_string()]
+    }
+
+    fn help(&self) -> String {
+        "handle = extract <archive> <destination> [--path <folder>] [--include <glob>] [--exclude <glob>] [--format <format>]\n\n\
+        Extracts a zip, tar, tar.gz, tar.xz or 7z archive into a folder.\n\
+        --path only extracts that folder of the archive, without the folder itself.\n\
+        --include and --exclude can be repeated and match paths relative to --path.\n\
+        Returns an array handle with the path of every extracted file."
+            .to_string()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, arguments: CommandArgs) -> CommandResult {
+        let parsed = match ExtractArgs::parse(&arguments.args) {
+            Ok(parsed) => parsed,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let (archive, destination) = match (parsed.archive, parsed.destination) {
+            (Some(archive), Some(destination)) => (
+                resolve(&arguments.args[archive]),
+                resolve(&arguments.args[destination]),
+            ),
+            _ => {
+                return CommandResult::Error(
+                    "Archive and destination paths are required".to_string(),
+                )
+            }
+        };
+
+        let format = match archive_format(&archive, parsed.format.as_deref()) {
+            Ok(format) => format,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let options = match ExtractOptions::new(parsed.sub_path, &parsed.include, &parsed.exclude) {
+            Ok(options) => options,
+            Err(e) => return CommandResult::Error(format!("Invalid glob: {}", e)),
+        };
+
+        match extract_archive(&archive, &destination, format, &options) {
+            Ok(files) => {
+                let files = files
+                    .iter()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .collect();
+                CommandResult::Continue(Some(put_array(arguments.state, files)))
+            }
+            Err(e) => CommandResult::Error(format!("Failed to extract {:?}: {}", archive, e)),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ArchiveListCommand;
+
+impl Command for ArchiveListCommand {
+    fn name(&self) -> String {
+        "archive_list".to_string()
+    }
+
+    fn help(&self) -> String {
+        "handle = archive_list <archive> [--format <format>]\n\n\
+        Returns an array handle with the path of every file inside an archive."
+            .to_string()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, arguments: CommandArgs) -> CommandResult {
+        let parsed = match ExtractArgs::parse(&arguments.args) {
+            Ok(parsed) => parsed,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let archive = match parsed.archive {
+            Some(archive) => resolve(&arguments.args[archive]),
+            None => return CommandResult::Error("Archive path is required".to_string()),
+        };
+        let format = match archive_format(&archive, parsed.format.as_deref()) {
+            Ok(format) => format,
+            Err(e) => return CommandResult::Error(e),
+        };
+
+        match list_archive(&archive, format) {
+            Ok(names) => CommandResult::Continue(Some(put_array(arguments.state, names))),
+            Err(e) => CommandResult::Error(format!("Failed to read {:?}: {}", archive, e)),
+        }
+    }
+}
+
+// Adds the archive commands to a command set
+pub fn load_archive_commands(commands: &mut Commands) -> Result<(), ScriptError> {
+    commands.set(Box::new(ExtractCommand))?;
+    commands.set(Box::new(ArchiveListCommand))?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::downloads::{DownloadManager, DownloadRequest};
+use crate::utils::files::absolute_path;
+use duckscript::types::command::{Command, CommandArgs, CommandResult};
+use std::path::Path;
+
+/*
+Arguments of `download`. Like ExtractArgs, positions are indexes into the
+argument list so the wrappers can rewrite the output path in place.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct DownloadArgs {
+    pub url: Option<usize>,
+    pub sha256: Option<String>,
+    pub output: Option<usize>,
+}
+
+impl DownloadArgs {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut index = 0;
+        while index < args.len() {
+            match args[index].as_str() {
+                "--sha256" | "--output" => {
+                    if index + 1 >= args.len() {
+                        return Err(format!("{} needs a value", args[index]));
+                    }
+                    if args[index] == "--sha256" {
+                        parsed.sha256 = Some(args[index + 1].clone());
+                    } else {
+                        parsed.output = Some(index + 1);
+                    }
+                    index += 2;
+                }
+                _ => {
+                    parsed.url.get_or_insert(index);
+                    index += 1;
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[derive(Clone)]
+pub struct DownloadCommand {
+    manager: DownloadManager,
+}
+
+impl DownloadCommand {
+    pub fn new(manager: DownloadManager) -> Self {
+        Self { manager }
+    }
+}
+
+impl Command for DownloadCommand {
+    fn name(&self) -> String {
+        "download".to_string()
+    }
+
+    fn help(&self) -> String {
+        "path = download <url> --sha256 <hash> [--output <file>]\n\n\
+        Downloads a file into Mud's download cache, resuming an earlier attempt if one was interrupted.\n\
+        The file must match --sha256, files already in the cache are not downloaded again.\n\
+        Returns --output when given (the file is copied there), otherwise the path in the cache."
+            .to_string()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, arguments: CommandArgs) -> CommandResult {
+        let parsed = match DownloadArgs::parse(&arguments.args) {
+            Ok(parsed) => parsed,
+            Err(e) => return CommandResult::Error(e),
+        };
+        let url = match parsed.url {
+            Some(url) => arguments.args[url].clone(),
+            None => return CommandResult::Error("URL is required".to_string()),
+        };
+        let sha256 = match parsed.sha256 {
+            Some(sha256) => sha256,
+            None => return CommandResult::Error(format!("--sha256 is required for {}", url)),
+        };
+        let destination = parsed.output.map(|i| {
+            let output = Path::new(&arguments.args[i]);
+            absolute_path(output).unwrap_or_else(|_| output.to_path_buf())
+        });
+
+        let request = DownloadRequest {
+            url,
+            sha256,
+            destination,
+        };
+        match self.manager.fetch(&request) {
+            Ok(path) => CommandResult::Continue(Some(path.to_string_lossy().to_string())),
+            Err(e) => CommandResult::Error(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use duckscript::types::runtime::StateValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_HANDLE: AtomicUsize = AtomicUsize::new(0);
+
+fn sub_state<'a>(
+    state: &'a mut HashMap<String, StateValue>,
+    key: &str,
+) -> &'a mut HashMap<String, StateValue> {
+    let value = state
+        .entry(key.to_string())
+        .or_insert_with(|| StateValue::SubState(HashMap::new()));
+    if !matches!(value, StateValue::SubState(_)) {
+        *value = StateValue::SubState(HashMap::new());
+    }
+    match value {
+        StateValue::SubState(sub_state) => sub_state,
+        _ => unreachable!(),
+    }
+}
+
+/*
+Stores a list where duckscriptsdk keeps its handles (state["duckscriptsdk"]["handles"]),
+so the returned handle works with array_length, array_get, for loops and release.
+*/
+pub fn put_array(state: &mut HashMap<String, StateValue>, values: Vec<String>) -> String {
+    let handles = sub_state(sub_state(state, "duckscriptsdk"), "handles");
+    let key = format!("mud-handle:{}", NEXT_HANDLE.fetch_add(1, Ordering::SeqCst));
+    let list = values.into_iter().map(StateValue::String).collect();
+    handles.insert(key.clone(), StateValue::List(list));
+    key
+}
+
+// Rewrites every value of a list stored by `put_array`
+pub fn map_array<F>(state: &mut HashMap<String, StateValue>, handle: &str, f: F)
+where
+    F: Fn(&str) -> String,
+{
+    let handles = sub_state(sub_state(state, "duckscriptsdk"), "handles");
+    if let Some(StateValue::List(values)) = handles.get_mut(handle) {
+        for value in values.iter_mut() {
+            if let StateValue::String(s) = value {
+                *s = f(s);
+            }
+        }
+    }
+}
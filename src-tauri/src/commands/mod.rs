@@ -0,0 +1,37 @@
+pub mod archive;
+pub mod download;
+pub mod handles;
+pub mod offline;
+pub mod process;
+pub mod recording;
+pub mod scoped;
+pub mod staged;
+pub mod tracked;
+
+pub use archive::*;
+pub use download::*;
+pub use handles::*;
+pub use offline::*;
+pub use process::*;
+pub use recording::*;
+pub use scoped::*;
+pub use staged::*;
+pub use tracked::*;
+
+use crate::context::environment::SharedScriptEnv;
+use crate::downloads::DownloadManager;
+use duckscript::types::command::Commands;
+use duckscript::types::error::ScriptError;
+
+// Mud's own commands, loaded on top of duckscriptsdk
+pub fn load_mud_commands(
+    commands: &mut Commands,
+    downloads: DownloadManager,
+    env: SharedScriptEnv,
+) -> Result<(), ScriptError> {
+    load_archive_commands(commands)?;
+    commands.set(Box::new(DownloadCommand::new(downloads)))?;
+    load_env_commands(commands, env.clone())?;
+    load_process_commands(commands, env)?;
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use duckscript::types::command::{Command, CommandArgs, CommandResult, Commands};
+use duckscript::types::error::ScriptError;
+
+// Commands that reach the network without going through the download cache
+pub static NETWORK_COMMANDS: &[&str] = &["wget", "http_client"];
+
+// Stand-in for a network command while offline mode is on
+#[derive(Clone)]
+pub struct OfflineCommand {
+    name: String,
+    aliases: Vec<String>,
+    help: String,
+}
+
+impl Command for OfflineCommand {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    fn help(&self) -> String {
+        self.help.clone()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, _arguments: CommandArgs) -> CommandResult {
+        CommandResult::Error(format!(
+            "{} is unavailable in offline mode, use `download <url> --sha256 <hash>` so the file can come from the download cache",
+            self.name
+        ))
+    }
+}
+
+// Swaps the network commands in a command set for ones that fail with an explanation
+pub fn block_network_commands(commands: &mut Commands) -> Result<(), ScriptError> {
+    for name in NETWORK_COMMANDS {
+        let (aliases, help) = match commands.get(name) {
+            Some(command) => (command.aliases(), command.help()),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(OfflineCommand {
+            name: name.to_string(),
+            aliases,
+            help,
+        }))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use crate::context::environment::{ScriptEnv, SharedScriptEnv};
+use duckscript::types::command::{Command, CommandArgs, CommandResult, Commands};
+use duckscript::types::error::ScriptError;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Child, Stdio};
+
+// duckscriptsdk commands that start processes, replaced so children get the run's cwd and env
+pub static PROCESS_COMMANDS: &[&str] = &["exec", "spawn"];
+
+/*
+Arguments of exec, spawn and watchdog, e.g.
+exec --get-exit-code --input "y" installer.sh --quiet
+watchdog --max-retries 3 --interval 100 -- server.sh --port 8080
+Everything after the program belongs to it, dashes included.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct ProcessArgs {
+    pub program: Option<usize>,
+    pub input: Option<usize>,
+    pub get_exit_code: bool,
+    pub fail_on_error: bool,
+    // spawn only, discards the child's output
+    pub silent: bool,
+}
+
+impl ProcessArgs {
+    pub fn parse(name: &str, args: &[String]) -> Self {
+        let mut parsed = Self::default();
+        if name == "watchdog" {
+            parsed.program = args
+                .iter()
+                .position(|a| a == "--")
+                .map(|i| i + 1)
+                .filter(|&i| i < args.len());
+            return parsed;
+        }
+        let mut index = 0;
+        while index < args.len() {
+            match args[index].as_str() {
+                "--input" => {
+                    parsed.input = Some(index + 1).filter(|&i| i < args.len());
+                    index += 2;
+                }
+                "--get-exit-code" => {
+                    parsed.get_exit_code = true;
+                    index += 1;
+                }
+                "--fail-on-error" => {
+                    parsed.fail_on_error = true;
+                    index += 1;
+                }
+                "--silent" => {
+                    parsed.silent = true;
+                    index += 1;
+                }
+                _ => {
+                    parsed.program = Some(index);
+                    break;
+                }
+            }
+        }
+        parsed
+    }
+}
+
+// Starts the program in the run's working directory with its env vars, writing `--input` to stdin
+fn start(
+    env: &ScriptEnv,
+    args: &[String],
+    parsed: &ProcessArgs,
+    output: Stdio,
+    errors: Stdio,
+) -> io::Result<Child> {
+    let program = parsed
+        .program
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Command name is required"))?;
+    // ./tool.sh is relative to the run's folder, bare names are looked up on the PATH
+    let path = Path::new(&args[program]);
+    let mut command = match path.components().count() > 1 && path.is_relative() {
+        true => std::process::Command::new(env.resolve(path)),
+        false => std::process::Command::new(path),
+    };
+    command.args(&args[program + 1..]);
+    env.apply(&mut command);
+    command
+        .stdin(match parsed.input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::inherit(),
+        })
+        .stdout(output)
+        .stderr(errors);
+
+    let mut child = command.spawn()?;
+    if let (Some(input), Some(mut stdin)) = (parsed.input, child.stdin.take()) {
+        stdin.write_all(args[input].as_bytes())?;
+    }
+    Ok(child)
+}
+
+// exec and spawn, with the same arguments and results as duckscriptsdk's
+#[derive(Clone)]
+pub struct ProcessCommand {
+    name: String,
+    aliases: Vec<String>,
+    help: String,
+    env: SharedScriptEnv,
+}
+
+impl ProcessCommand {
+    fn exec(&self, env: &ScriptEnv, arguments: CommandArgs) -> CommandResult {
+        let parsed = ProcessArgs::parse(&self.name, &arguments.args);
+        let output = start(
+            env,
+            &arguments.args,
+            &parsed,
+            Stdio::piped(),
+            Stdio::piped(),
+        )
+        .and_then(|child| child.wait_with_output());
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => return CommandResult::Error(format!("Failed to run command: {}", e)),
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let code = output.status.code().unwrap_or(-1);
+
+        // Output that isn't captured into a variable goes to the script's output
+        if arguments.output_variable.is_none() {
+            let _ = arguments.env.out.write_all(stdout.as_bytes());
+            let _ = arguments.env.err.write_all(stderr.as_bytes());
+        }
+        if parsed.fail_on_error && code != 0 {
+            return CommandResult::Error(format!(
+                "Error while executing command, exit code: {}",
+                code
+            ));
+        }
+        if parsed.get_exit_code {
+            return CommandResult::Continue(Some(code.to_string()));
+        }
+        if let Some(output) = arguments.output_variable {
+            for (suffix, value) in [
+                ("code", code.to_string()),
+                ("stdout", stdout),
+                ("stderr", stderr),
+            ] {
+                arguments
+                    .variables
+                    .insert(format!("{}.{}", output, suffix), value);
+            }
+        }
+        CommandResult::Continue(None)
+    }
+
+    fn spawn(&self, env: &ScriptEnv, arguments: CommandArgs) -> CommandResult {
+        let parsed = ProcessArgs::parse(&self.name, &arguments.args);
+        let output = || match parsed.silent {
+            true => Stdio::null(),
+            false => Stdio::inherit(),
+        };
+        match start(env, &arguments.args, &parsed, output(), output()) {
+            Ok(child) => CommandResult::Continue(Some(child.id().to_string())),
+            Err(e) => CommandResult::Error(format!("Failed to spawn command: {}", e)),
+        }
+    }
+}
+
+impl Command for ProcessCommand {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    fn help(&self) -> String {
+        self.help.clone()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, arguments: CommandArgs) -> CommandResult {
+        // A snapshot, so a long running child doesn't hold the lock
+        let env = match self.env.lock() {
+            Ok(env) => env.clone(),
+            Err(e) => return CommandResult::Error(e.to_string()),
+        };
+        match self.name.as_str() {
+            "spawn" => self.spawn(&env, arguments),
+            _ => self.exec(&env, arguments),
+        }
+    }
+}
+
+// Swaps exec and spawn for versions that run children in the run's cwd and env
+pub fn load_process_commands(
+    commands: &mut Commands,
+    env: SharedScriptEnv,
+) -> Result<(), ScriptError> {
+    for name in PROCESS_COMMANDS {
+        let (aliases, help) = match commands.get(name) {
+            Some(command) => (command.aliases(), command.help()),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(ProcessCommand {
+            name: name.to_string(),
+            aliases,
+            help,
+            env: env.clone(),
+        }))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,255 @@
+use crate::commands::archive::ExtractArgs;
+use crate::commands::download::DownloadArgs;
+use crate::commands::handles::put_array;
+use crate::commands::process::ProcessArgs;
+use crate::commands::tracked::copy_destinations;
+use crate::output::{PlannedDownload, PlannedFile, PlannedProcess, ScriptPlan, SharedPlan};
+use crate::utils::files::absolute_path;
+use duckscript::types::command::{Command, CommandArgs, CommandResult, Commands};
+use duckscript::types::error::ScriptError;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlanKind {
+    Write,
+    Delete,
+    Move,
+    Download,
+    Process,
+}
+
+// Commands with side effects outside the script, and what a dry run records for them
+pub static RECORDED_COMMANDS: &[(&str, PlanKind)] = &[
+    ("writefile", PlanKind::Write),
+    ("writebinfile", PlanKind::Write),
+    ("appendfile", PlanKind::Write),
+    ("touch", PlanKind::Write),
+    ("mkdir", PlanKind::Write),
+    ("cp", PlanKind::Write),
+    ("chmod", PlanKind::Write),
+    ("extract", PlanKind::Write),
+    ("mv", PlanKind::Move),
+    ("rm", PlanKind::Delete),
+    ("rmdir", PlanKind::Delete),
+    ("wget", PlanKind::Download),
+    ("http_client", PlanKind::Download),
+    ("download", PlanKind::Download),
+    ("exec", PlanKind::Process),
+    ("spawn", PlanKind::Process),
+    ("watchdog", PlanKind::Process),
+];
+
+pub fn positional(args: &[String]) -> Vec<usize> {
+    args.iter()
+        .enumerate()
+        .filter(|(_, a)| !a.starts_with('-'))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/*
+Arguments of wget and http_client, e.g.
+wget --method=HEAD -O file.zip https://example.com/file.zip
+http_client --method GET --output-file file.zip https://example.com/file.zip
+*/
+#[derive(Clone, Debug, Default)]
+pub struct FetchArgs {
+    pub url: Option<usize>,
+    pub output: Option<usize>,
+}
+
+impl FetchArgs {
+    pub fn parse(name: &str, args: &[String]) -> Self {
+        let (output_option, value_options): (&str, &[&str]) = match name {
+            "wget" => ("-O", &[]),
+            _ => ("--output-file", &["--method", "--payload"]),
+        };
+        let mut parsed = Self::default();
+        let mut index = 0;
+        while index < args.len() {
+            let arg = args[index].as_str();
+            if arg == output_option {
+                parsed.output = Some(index + 1).filter(|&i| i < args.len());
+                index += 2;
+            } else if value_options.contains(&arg) {
+                index += 2;
+            } else {
+                // wget passes option values as --key=value
+                if !arg.starts_with("--") {
+                    parsed.url.get_or_insert(index);
+                }
+                index += 1;
+            }
+        }
+        parsed
+    }
+}
+
+fn plan_path(arg: &str) -> PathBuf {
+    absolute_path(Path::new(arg)).unwrap_or_else(|_| PathBuf::from(arg))
+}
+
+/*
+Stand-in for a command with side effects. Records what would have happened
+into the plan and returns a plausible result so the script keeps going.
+*/
+#[derive(Clone)]
+pub struct RecordingCommand {
+    name: String,
+    aliases: Vec<String>,
+    help: String,
+    kind: PlanKind,
+    plan: SharedPlan,
+}
+
+impl RecordingCommand {
+    fn record(&self, plan: &mut ScriptPlan, args: &[String], line: usize) {
+        let command = self.name.clone();
+        let file = |path: PathBuf| PlannedFile {
+            path,
+            command: command.clone(),
+            line,
+            source_file: None,
+        };
+        let arg_path = |i: usize| plan_path(&args[i]);
+        // cp and mv copy into a folder target, like the tracked versions
+        let copied = || {
+            let paths: Vec<PathBuf> = positional(args).into_iter().map(arg_path).collect();
+            copy_destinations(&paths)
+        };
+
+        match self.kind {
+            PlanKind::Write => {
+                let targets: Vec<PathBuf> = match self.name.as_str() {
+                    "cp" => copied(),
+                    // chmod <mode> <path>
+                    "chmod" => args.get(1).map(|p| plan_path(p)).into_iter().collect(),
+                    "extract" => ExtractArgs::parse(args)
+                        .ok()
+                        .and_then(|parsed| parsed.destination)
+                        .map(arg_path)
+                        .into_iter()
+                        .collect(),
+                    // writefile <path> <text> and friends, the rest is content
+                    _ => args.first().map(|p| plan_path(p)).into_iter().collect(),
+                };
+                plan.writes.extend(targets.into_iter().map(file));
+            }
+            PlanKind::Delete => {
+                let targets: Vec<usize> = match self.name.as_str() {
+                    "rmdir" => args.first().map(|_| 0).into_iter().collect(),
+                    // rm [-r] <path>+
+                    _ => positional(args),
+                };
+                plan.deletes
+                    .extend(targets.into_iter().map(arg_path).map(file));
+            }
+            PlanKind::Move => {
+                if let [source, _] = positional(args)[..] {
+                    plan.deletes.push(file(arg_path(source)));
+                    plan.writes.extend(copied().into_iter().map(file));
+                }
+            }
+            PlanKind::Download if self.name == "download" => {
+                let parsed = DownloadArgs::parse(args).unwrap_or_default();
+                plan.downloads.push(PlannedDownload {
+                    url: parsed.url.map(|i| args[i].clone()).unwrap_or_default(),
+                    sha256: parsed.sha256,
+                    destination: parsed.output.map(|i| plan_path(&args[i])),
+                    command,
+                    line,
+                    source_file: None,
+                });
+            }
+            PlanKind::Download => {
+                let parsed = FetchArgs::parse(&self.name, args);
+                plan.downloads.push(PlannedDownload {
+                    url: parsed.url.map(|i| args[i].clone()).unwrap_or_default(),
+                    sha256: None,
+                    destination: parsed.output.map(arg_path),
+                    command,
+                    line,
+                    source_file: None,
+                });
+            }
+            PlanKind::Process => {
+                let program = ProcessArgs::parse(&self.name, args).program;
+                plan.processes.push(PlannedProcess {
+                    program: program.map(|i| args[i].clone()).unwrap_or_default(),
+                    args: program.map(|i| args[i + 1..].to_vec()).unwrap_or_default(),
+                    command,
+                    line,
+                    source_file: None,
+                    assumed_exit_code: 0,
+                });
+            }
+        }
+    }
+}
+
+impl Command for RecordingCommand {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    fn help(&self) -> String {
+        self.help.clone()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, arguments: CommandArgs) -> CommandResult {
+        if let Ok(mut plan) = self.plan.lock() {
+            self.record(&mut plan, &arguments.args, arguments.line);
+        }
+
+        match self.kind {
+            PlanKind::Process => {
+                // Processes are assumed to succeed, the plan notes that with assumed_exit_code
+                if ProcessArgs::parse(&self.name, &arguments.args).get_exit_code {
+                    return CommandResult::Continue(Some("0".to_string()));
+                }
+                // exec exposes its result as <out>.code/.stdout/.stderr
+                if let Some(output) = arguments.output_variable {
+                    for (suffix, value) in [("code", "0"), ("stdout", ""), ("stderr", "")] {
+                        arguments
+                            .variables
+                            .insert(format!("{}.{}", output, suffix), value.to_string());
+                    }
+                }
+                CommandResult::Continue(None)
+            }
+            PlanKind::Download => CommandResult::Continue(Some(String::new())),
+            // No files are extracted, but the script still gets an array to loop over
+            PlanKind::Write if self.name == "extract" => {
+                CommandResult::Continue(Some(put_array(arguments.state, Vec::new())))
+            }
+            _ => CommandResult::Continue(Some("true".to_string())),
+        }
+    }
+}
+
+// Swaps every command with side effects for a recording stub
+pub fn record_commands(commands: &mut Commands, plan: SharedPlan) -> Result<(), ScriptError> {
+    for (name, kind) in RECORDED_COMMANDS {
+        let (aliases, help) = match commands.get(name) {
+            Some(command) => (command.aliases(), command.help()),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(RecordingCommand {
+            name: name.to_string(),
+            aliases,
+            help,
+            kind: *kind,
+            plan: plan.clone(),
+        }))?;
+    }
+    Ok(())
+}
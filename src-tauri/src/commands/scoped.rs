@@ -0,0 +1,269 @@
+use crate::commands::archive::ExtractArgs;
+use crate::commands::download::DownloadArgs;
+use crate::commands::recording::{positional, FetchArgs};
+use crate::context::environment::{is_reserved_var, SharedScriptEnv};
+use duckscript::types::command::{Command, CommandArgs, CommandResult, Commands};
+use duckscript::types::error::ScriptError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Commands whose first argument is a path, e.g. writefile <path> <text>
+static FIRST_PATH_COMMANDS: &[&str] = &[
+    "readfile",
+    "readbinfile",
+    "writefile",
+    "writebinfile",
+    "appendfile",
+    "touch",
+    "mkdir",
+    "rmdir",
+    "is_path_exists",
+    "is_directory",
+    "is_file",
+    "is_readonly",
+    "get_file_size",
+    "get_last_modified_time",
+    "canonicalize",
+    "glob_array",
+    "gitignore_path_array",
+];
+
+// Commands where every positional argument is a path
+static ALL_PATH_COMMANDS: &[&str] = &["cp", "mv", "rm", "ls", "glob_cp", "is_path_newer"];
+
+// Commands with their own argument parsing, see path_args
+static PARSED_PATH_COMMANDS: &[&str] = &[
+    "chmod",
+    "extract",
+    "archive_list",
+    "download",
+    "wget",
+    "http_client",
+];
+
+// Indexes of the arguments a command reads as paths
+pub fn path_args(name: &str, args: &[String]) -> Vec<usize> {
+    match name {
+        // chmod <mode> <path>
+        "chmod" => (args.len() > 1).then_some(1).into_iter().collect(),
+        "extract" | "archive_list" => ExtractArgs::parse(args)
+            .map(|parsed| {
+                parsed
+                    .archive
+                    .into_iter()
+                    .chain(parsed.destination)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        "download" => DownloadArgs::parse(args)
+            .ok()
+            .and_then(|parsed| parsed.output)
+            .into_iter()
+            .collect(),
+        "wget" | "http_client" => FetchArgs::parse(name, args).output.into_iter().collect(),
+        _ if ALL_PATH_COMMANDS.contains(&name) => positional(args),
+        _ if FIRST_PATH_COMMANDS.contains(&name) => {
+            (!args.is_empty()).then_some(0).into_iter().collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/*
+Wraps a command that takes paths so relative ones are resolved against the
+run's working directory instead of the app's. Loaded last, so the tracked,
+staged and recording layers underneath only ever see absolute paths.
+*/
+pub struct ScopedCommand {
+    inner: Box<dyn Command>,
+    env: SharedScriptEnv,
+}
+
+impl Clone for ScopedCommand {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_and_box(),
+            env: self.env.clone(),
+        }
+    }
+}
+
+impl Command for ScopedCommand {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.inner.aliases()
+    }
+
+    fn help(&self) -> String {
+        self.inner.help()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, mut arguments: CommandArgs) -> CommandResult {
+        if let Ok(env) = self.env.lock() {
+            for index in path_args(&self.inner.name(), &arguments.args) {
+                let path = Path::new(&arguments.args[index]);
+                if path.is_relative() {
+                    arguments.args[index] = env.resolve(path).to_string_lossy().to_string();
+                }
+            }
+        }
+        self.inner.run(arguments)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvCommandKind {
+    Cd,
+    Pwd,
+    SetEnv,
+    GetEnv,
+    UnsetEnv,
+}
+
+// duckscriptsdk commands that would change the app's own cwd or env
+pub static ENV_COMMANDS: &[(&str, EnvCommandKind)] = &[
+    ("cd", EnvCommandKind::Cd),
+    ("pwd", EnvCommandKind::Pwd),
+    ("set_env", EnvCommandKind::SetEnv),
+    ("get_env", EnvCommandKind::GetEnv),
+    ("unset_env", EnvCommandKind::UnsetEnv),
+];
+
+// Stand-in for a cwd or env command that works on the run's env instead
+#[derive(Clone)]
+pub struct EnvCommand {
+    name: String,
+    aliases: Vec<String>,
+    help: String,
+    kind: EnvCommandKind,
+    env: SharedScriptEnv,
+}
+
+impl Command for EnvCommand {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    fn help(&self) -> String {
+        self.help.clone()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, arguments: CommandArgs) -> CommandResult {
+        let mut env = match self.env.lock() {
+            Ok(env) => env,
+            Err(e) => return CommandResult::Error(e.to_string()),
+        };
+        let args = &arguments.args;
+        match self.kind {
+            EnvCommandKind::Cd => {
+                // Like a shell, no path means the home folder
+                let target = match args.first() {
+                    Some(path) => env.resolve(Path::new(path)),
+                    None => match env.var("HOME").or_else(|| env.var("USERPROFILE")) {
+                        Some(home) => PathBuf::from(home),
+                        None => return CommandResult::Error("No home folder to go to".to_string()),
+                    },
+                };
+                if !target.is_dir() {
+                    return CommandResult::Error(format!("{:?} is not a folder", target));
+                }
+                let target_name = target.to_string_lossy().to_string();
+                env.cwd = Some(target);
+                CommandResult::Continue(Some(target_name))
+            }
+            EnvCommandKind::Pwd => {
+                let cwd = match &env.cwd {
+                    Some(cwd) => cwd.clone(),
+                    // Runs without a folder of their own still use the app's
+                    None => match std::env::current_dir() {
+                        Ok(cwd) => cwd,
+                        Err(e) => return CommandResult::Error(e.to_string()),
+                    },
+                };
+                let cwd = cwd.to_string_lossy().to_string();
+                let _ = writeln!(arguments.env.out, "{}", cwd);
+                CommandResult::Continue(Some(cwd))
+            }
+            EnvCommandKind::SetEnv => match &args[..] {
+                [key, _] if is_reserved_var(key) => {
+                    CommandResult::Error(format!("{} is set by Mud and can't be changed", key))
+                }
+                [key, value] => {
+                    env.set_var(key, value);
+                    CommandResult::Continue(None)
+                }
+                _ => CommandResult::Error("set_env takes a name and a value".to_string()),
+            },
+            EnvCommandKind::GetEnv => match args.first() {
+                Some(key) => CommandResult::Continue(env.var(key)),
+                None => CommandResult::Error("Env var name is required".to_string()),
+            },
+            EnvCommandKind::UnsetEnv => {
+                if let Some(key) = args.iter().find(|key| is_reserved_var(key)) {
+                    return CommandResult::Error(format!(
+                        "{} is set by Mud and can't be unset",
+                        key
+                    ));
+                }
+                for key in args {
+                    env.remove_var(key);
+                }
+                CommandResult::Continue(None)
+            }
+        }
+    }
+}
+
+// Swaps the cwd and env commands for ones scoped to the run
+pub fn load_env_commands(commands: &mut Commands, env: SharedScriptEnv) -> Result<(), ScriptError> {
+    for (name, kind) in ENV_COMMANDS {
+        let (aliases, help) = match commands.get(name) {
+            Some(command) => (command.aliases(), command.help()),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(EnvCommand {
+            name: name.to_string(),
+            aliases,
+            help,
+            kind: *kind,
+            env: env.clone(),
+        }))?;
+    }
+    Ok(())
+}
+
+// Wraps every command that takes paths, call it after the other layers are loaded
+pub fn scope_commands(commands: &mut Commands, env: SharedScriptEnv) -> Result<(), ScriptError> {
+    let names = FIRST_PATH_COMMANDS
+        .iter()
+        .chain(ALL_PATH_COMMANDS)
+        .chain(PARSED_PATH_COMMANDS);
+    for name in names {
+        let inner = match commands.get(name) {
+            Some(command) => command.clone_and_box(),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(ScopedCommand {
+            inner,
+            env: env.clone(),
+        }))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use crate::commands::archive::ExtractArgs;
+use crate::commands::download::DownloadArgs;
+use crate::commands::handles::map_array;
+use crate::mods::{SharedTransaction, Transaction};
+use crate::utils::files::{absolute_path, copy_path};
+use duckscript::types::command::{Command, CommandArgs, CommandResult, Commands};
+use duckscript::types::error::ScriptError;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// duckscriptsdk commands whose paths are redirected into the transaction's shadow directory
+pub static STAGED_COMMANDS: &[&str] = &[
+    "writefile",
+    "writebinfile",
+    "appendfile",
+    "touch",
+    "mkdir",
+    "chmod",
+    "cp",
+    "mv",
+    "rm",
+    "rmdir",
+    "readfile",
+    "readbinfile",
+    "is_path_exists",
+    "is_file",
+    "is_directory",
+    "ls",
+    "extract",
+    "archive_list",
+    "download",
+];
+
+// Commands whose writes can't be redirected, so a transactional run refuses them
+pub static UNSTAGED_COMMANDS: &[&str] = &["exec", "spawn", "watchdog", "glob_cp"];
+
+fn path_arg_indexes(args: &[String]) -> Vec<usize> {
+    args.iter()
+        .enumerate()
+        .filter(|(_, a)| !a.starts_with('-'))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn resolve(arg: &str) -> io::Result<PathBuf> {
+    absolute_path(Path::new(arg))
+}
+
+/*
+Wraps a duckscriptsdk command so that writes go to the shadow directory
+and reads see what the script has staged so far.
+*/
+pub struct StagedCommand {
+    inner: Box<dyn Command>,
+    transaction: SharedTransaction,
+}
+
+impl Clone for StagedCommand {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_and_box(),
+            transaction: self.transaction.clone(),
+        }
+    }
+}
+
+impl StagedCommand {
+    // Rewrites path arguments in place, returning an early result for commands handled here
+    fn stage(
+        &self,
+        transaction: &mut Transaction,
+        args: &mut [String],
+    ) -> io::Result<Option<CommandResult>> {
+        let paths = path_arg_indexes(args);
+        match self.inner.name().as_str() {
+            "extract" | "archive_list" => {
+                let parsed = ExtractArgs::parse(args).map_err(io::Error::other)?;
+                if let Some(i) = parsed.archive {
+                    let read = transaction.resolve_read(&resolve(&args[i])?);
+                    args[i] = read.to_string_lossy().to_string();
+                }
+                if let Some(i) = parsed.destination {
+                    let shadow = transaction.stage_write(&resolve(&args[i])?)?;
+                    args[i] = shadow.to_string_lossy().to_string();
+                }
+                Ok(None)
+            }
+            "download" => {
+                // Without --output the file only lands in the download cache
+                let parsed = DownloadArgs::parse(args).map_err(io::Error::other)?;
+                if let Some(i) = parsed.output {
+                    let shadow = transaction.stage_write(&resolve(&args[i])?)?;
+                    args[i] = shadow.to_string_lossy().to_string();
+                }
+                Ok(None)
+            }
+            "writefile" | "writebinfile" | "appendfile" | "touch" | "mkdir" | "chmod" => {
+                // chmod's first argument is the mode
+                let path = match self.inner.name().as_str() {
+                    "chmod" => paths.get(1),
+                    _ => paths.first(),
+                };
+                if let Some(&i) = path {
+                    let shadow = transaction.stage_write(&resolve(&args[i])?)?;
+                    args[i] = shadow.to_string_lossy().to_string();
+                }
+                Ok(None)
+            }
+            "cp" => {
+                if let Some((&target, sources)) = paths.split_last() {
+                    for &i in sources {
+                        let source = transaction.resolve_read(&resolve(&args[i])?);
+                        args[i] = source.to_string_lossy().to_string();
+                    }
+                    let shadow = transaction.stage_write(&resolve(&args[target])?)?;
+                    args[target] = shadow.to_string_lossy().to_string();
+                }
+                Ok(None)
+            }
+            "mv" => {
+                // The real source can't move yet, so copy it into the shadow and drop it at commit
+                if let [source, target] = paths[..] {
+                    let real_source = resolve(&args[source])?;
+                    let from = transaction.resolve_read(&real_source);
+                    // A folder staged earlier in this run only exists in the shadow
+                    let real_target = resolve(&args[target])?;
+                    let real_target = if transaction.resolve_read(&real_target).is_dir() {
+                        real_target.join(real_source.file_name().unwrap_or_default())
+                    } else {
+                        real_target
+                    };
+                    let to = transaction.stage_write(&real_target)?;
+                    copy_path(&from, &to)?;
+                    transaction.stage_remove(&real_source)?;
+                    return Ok(Some(CommandResult::Continue(Some("true".to_string()))));
+                }
+                Ok(None)
+            }
+            "rm" | "rmdir" => {
+                // The real command runs on shadow copies so it fails the way it would on the real paths
+                for &i in &paths {
+                    let shadow = transaction.stage_write(&resolve(&args[i])?)?;
+                    args[i] = shadow.to_string_lossy().to_string();
+                }
+                Ok(None)
+            }
+            _ => {
+                for &i in &paths {
+                    let read = transaction.resolve_read(&resolve(&args[i])?);
+                    args[i] = read.to_string_lossy().to_string();
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl Command for StagedCommand {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.inner.aliases()
+    }
+
+    fn help(&self) -> String {
+        self.inner.help()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, mut arguments: CommandArgs) -> CommandResult {
+        let original_args = arguments.args.clone();
+        let staged = match self.transaction.lock() {
+            Ok(mut transaction) => self.stage(&mut transaction, &mut arguments.args),
+            Err(e) => return CommandResult::Crash(e.to_string()),
+        };
+        match staged {
+            Ok(Some(result)) => result,
+            Ok(None) if self.inner.name() == "extract" => {
+                let staged_args = arguments.args.clone();
+                let state = &mut *arguments.state;
+                let result = self.inner.run(CommandArgs { state, ..arguments });
+                // Report where files will end up after commit, not their shadow copies
+                if let CommandResult::Continue(Some(ref handle)) = result {
+                    if let Some(i) = ExtractArgs::parse(&staged_args)
+                        .ok()
+                        .and_then(|p| p.destination)
+                    {
+                        let shadow = resolve(&staged_args[i]).unwrap_or_default();
+                        let real = resolve(&original_args[i]).unwrap_or_default();
+                        map_array(state, handle, |path| {
+                            match Path::new(path).strip_prefix(&shadow) {
+                                Ok(relative) => real.join(relative).to_string_lossy().to_string(),
+                                Err(_) => path.to_string(),
+                            }
+                        });
+                    }
+                }
+                result
+            }
+            Ok(None) if self.inner.name() == "download" => {
+                let staged_args = arguments.args.clone();
+                let result = self.inner.run(arguments);
+                match (
+                    result,
+                    DownloadArgs::parse(&staged_args)
+                        .ok()
+                        .and_then(|p| p.output),
+                ) {
+                    // Same as extract, report the path the file is committed to
+                    (CommandResult::Continue(Some(_)), Some(i)) => CommandResult::Continue(Some(
+                        resolve(&original_args[i])
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                    )),
+                    (result, _) => result,
+                }
+            }
+            Ok(None) if matches!(self.inner.name().as_str(), "rm" | "rmdir") => {
+                let result = self.inner.run(arguments);
+                if matches!(result, CommandResult::Error(_) | CommandResult::Crash(_)) {
+                    return result;
+                }
+                // Whatever the command removed from the shadow gets removed for real at commit
+                let mut transaction = match self.transaction.lock() {
+                    Ok(transaction) => transaction,
+                    Err(e) => return CommandResult::Crash(e.to_string()),
+                };
+                for &i in &path_arg_indexes(&original_args) {
+                    let real = match resolve(&original_args[i]) {
+                        Ok(real) => real,
+                        Err(_) => continue,
+                    };
+                    if !transaction.shadow_path(&real).exists() {
+                        if let Err(e) = transaction.stage_remove(&real) {
+                            return CommandResult::Error(format!(
+                                "Failed to stage {}: {}",
+                                self.name(),
+                                e
+                            ));
+                        }
+                    }
+                }
+                result
+            }
+            Ok(None) => self.inner.run(arguments),
+            Err(e) => CommandResult::Error(format!("Failed to stage {}: {}", self.name(), e)),
+        }
+    }
+}
+
+// Stand-in for a command whose side effects a transaction can't stage
+#[derive(Clone)]
+pub struct UnstagedCommand {
+    name: String,
+    aliases: Vec<String>,
+    help: String,
+}
+
+impl Command for UnstagedCommand {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    fn help(&self) -> String {
+        self.help.clone()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, _arguments: CommandArgs) -> CommandResult {
+        CommandResult::Error(format!(
+            "{} is unavailable in a transactional run, its changes couldn't be staged",
+            self.name
+        ))
+    }
+}
+
+// Replaces filesystem commands in a command set with staged versions
+pub fn stage_commands(
+    commands: &mut Commands,
+    transaction: SharedTransaction,
+) -> Result<(), ScriptError> {
+    for name in STAGED_COMMANDS {
+        let inner = match commands.get(name) {
+            Some(command) => command.clone_and_box(),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(StagedCommand {
+            inner,
+            transaction: transaction.clone(),
+        }))?;
+    }
+    for name in UNSTAGED_COMMANDS {
+        let (aliases, help) = match commands.get(name) {
+            Some(command) => (command.aliases(), command.help()),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(UnstagedCommand {
+            name: name.to_string(),
+            aliases,
+            help,
+        }))?;
+    }
+    Ok(())
+}
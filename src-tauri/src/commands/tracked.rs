@@ -0,0 +1,139 @@
+use crate::commands::archive::ExtractArgs;
+use crate::commands::download::DownloadArgs;
+use crate::mods::SharedSession;
+use crate::utils::files::absolute_path;
+use duckscript::types::command::{Command, CommandArgs, CommandResult, Commands};
+use duckscript::types::error::ScriptError;
+use std::path::{Path, PathBuf};
+
+/*
+duckscriptsdk commands that write to or remove from the filesystem. The ledger
+records file contents, so mkdir, rmdir and chmod aren't tracked: they change no
+file's contents, and folders they create are left behind on uninstall.
+*/
+pub static TRACKED_COMMANDS: &[&str] = &[
+    "writefile",
+    "writebinfile",
+    "appendfile",
+    "touch",
+    "cp",
+    "mv",
+    "rm",
+    "extract",
+    "download",
+];
+
+// Paths a filesystem command is about to modify, based on its arguments
+pub fn write_targets(name: &str, args: &[String]) -> Vec<PathBuf> {
+    // Only the files the archive will put there, not the whole destination folder
+    if name == "extract" {
+        return ExtractArgs::parse(args)
+            .and_then(|parsed| parsed.targets(args))
+            .unwrap_or_default();
+    }
+
+    if name == "download" {
+        return DownloadArgs::parse(args)
+            .ok()
+            .and_then(|parsed| parsed.output)
+            .and_then(|i| absolute_path(Path::new(&args[i])).ok())
+            .into_iter()
+            .collect();
+    }
+
+    let paths: Vec<PathBuf> = args
+        .iter()
+        .filter(|a| !a.starts_with('-'))
+        .filter_map(|a| absolute_path(Path::new(a)).ok())
+        .collect();
+    match name {
+        "cp" => copy_destinations(&paths),
+        "mv" => {
+            let mut targets = copy_destinations(&paths);
+            targets.extend(paths.iter().take(paths.len().saturating_sub(1)).cloned());
+            targets
+        }
+        "rm" => paths,
+        _ => paths.into_iter().take(1).collect(),
+    }
+}
+
+// Where `cp`/`mv` put their sources: inside the target when it's a folder, else the target itself
+pub fn copy_destinations(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let (target, sources) = match paths.split_last() {
+        Some((target, sources)) if !sources.is_empty() => (target, sources),
+        _ => return Vec::new(),
+    };
+    if !target.is_dir() {
+        return vec![target.clone()];
+    }
+    sources
+        .iter()
+        .filter_map(|source| source.file_name())
+        .map(|name| target.join(name))
+        .collect()
+}
+
+/*
+Wraps a duckscriptsdk command so the install session can back up
+every file it touches before the real command runs.
+*/
+pub struct TrackedCommand {
+    inner: Box<dyn Command>,
+    session: SharedSession,
+}
+
+impl Clone for TrackedCommand {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone_and_box(),
+            session: self.session.clone(),
+        }
+    }
+}
+
+impl Command for TrackedCommand {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        self.inner.aliases()
+    }
+
+    fn help(&self) -> String {
+        self.inner.help()
+    }
+
+    fn clone_and_box(&self) -> Box<dyn Command> {
+        Box::new(self.clone())
+    }
+
+    fn run(&self, arguments: CommandArgs) -> CommandResult {
+        let targets = write_targets(&self.inner.name(), &arguments.args);
+        if let Ok(mut session) = self.session.lock() {
+            for target in targets {
+                if let Err(e) = session.track(&target) {
+                    return CommandResult::Error(format!("Failed to back up {:?}: {}", target, e));
+                }
+            }
+        }
+        self.inner.run(arguments)
+    }
+}
+
+// Replaces the filesystem commands in a command set with tracked versions
+pub fn track_commands(commands: &mut Commands, session: SharedSession) -> Result<(), ScriptError> {
+    for name in TRACKED_COMMANDS {
+        let inner = match commands.get(name) {
+            Some(command) => command.clone_and_box(),
+            None => continue,
+        };
+        commands.remove(name);
+        commands.set(Box::new(TrackedCommand {
+            inner,
+            session: session.clone(),
+        }))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::utils::files::{absolute_path, normalize_path};
+use duckscript::types::runtime::Context;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+pub static MUD_GAME_DIR: &str = "MUD_GAME_DIR";
+pub static MUD_APPDATA: &str = "MUD_APPDATA";
+pub static MUD_REPO: &str = "MUD_REPO";
+pub static MUD_GAME_ID: &str = "MUD_GAME_ID";
+
+// Prefix of the vars the app sets for a run, scripts can read but not change them
+pub static MUD_VAR_PREFIX: &str = "MUD_";
+
+pub fn is_reserved_var(key: &str) -> bool {
+    // Env var names are case insensitive on Windows
+    key.to_ascii_uppercase().starts_with(MUD_VAR_PREFIX)
+}
+
+pub type SharedScriptEnv = Arc<Mutex<ScriptEnv>>;
+
+/*
+Working directory and env vars of one script run. They're never applied to the
+app's process, the path commands resolve relative paths against `cwd` and
+child processes get `vars` on top of the app's own env, see commands/scoped.rs.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct ScriptEnv {
+    pub cwd: Option<PathBuf>,
+    pub vars: BTreeMap<String, String>,
+    // Vars the script unset, hidden from child processes
+    pub removed: BTreeSet<String>,
+}
+
+impl ScriptEnv {
+    pub fn shared(self) -> SharedScriptEnv {
+        Arc::new(Mutex::new(self))
+    }
+
+    // Resolves a script supplied path against the run's working directory
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.cwd {
+            Some(cwd) if path.is_relative() => normalize_path(&cwd.join(path)),
+            _ => absolute_path(path).unwrap_or_else(|_| path.to_path_buf()),
+        }
+    }
+
+    pub fn var(&self, key: &str) -> Option<String> {
+        if self.removed.contains(key) {
+            return None;
+        }
+        self.vars
+            .get(key)
+            .cloned()
+            .or_else(|| std::env::var(key).ok())
+    }
+
+    pub fn set_var(&mut self, key: &str, value: &str) {
+        self.removed.remove(key);
+        self.vars.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn remove_var(&mut self, key: &str) {
+        self.vars.remove(key);
+        self.removed.insert(key.to_string());
+    }
+
+    // Runs a child process in the run's working directory with its env vars
+    pub fn apply(&self, command: &mut Command) {
+        if let Some(ref cwd) = self.cwd {
+            command.current_dir(cwd);
+        }
+        for key in &self.removed {
+            command.env_remove(key);
+        }
+        command.envs(&self.vars);
+    }
+
+    // Exposes the vars as script variables, e.g. ${MUD_GAME_DIR}
+    pub fn setup_context(&self, context: &mut Context) {
+        for (key, value) in &self.vars {
+            context.variables.insert(key.clone(), value.clone());
+        }
+    }
+}
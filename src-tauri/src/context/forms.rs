@@ -1,13 +1,50 @@
 use serde_json::Value;
+use std::path::Path;
+use std::sync::OnceLock;
 use tera::{Context, Tera};
 
-// Renders from serde_json serialized Value
-pub fn generate_form_html(json_data: Value, template_path: Option<&str>) -> Result<String, String> {
-    let tera = Tera::new("**/templates/*.html.tera").map_err(|e| e.to_string())?;
+pub static DEFAULT_TEMPLATE: &str = "config.html.tera";
+pub static GAME_TEMPLATE_FILE: &str = "config.html.tera";
+
+// Templates are embedded in the binary and compiled once on first use
+static TERA: OnceLock<Result<Tera, String>> = OnceLock::new();
+
+fn embedded_tera() -> Result<&'static Tera, String> {
+    TERA.get_or_init(|| {
+        let mut tera = Tera::default();
+        tera.add_raw_template(
+            DEFAULT_TEMPLATE,
+            include_str!("../../templates/config.html.tera"),
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(tera)
+    })
+    .as_ref()
+    .map_err(|e| e.clone())
+}
+
+// Renders from serde_json serialized Value, using a game's own template if one is shipped
+pub fn generate_form_html(
+    json_data: Value,
+    game_template: Option<&Path>,
+) -> Result<String, String> {
+    let tera = embedded_tera()?;
     let mut context = Context::new();
     context.insert("data", &json_data);
-    let template = template_path.unwrap_or("templates/config.html.tera");
 
-    let rendered_html = tera.render(template, &context).map_err(|e| e.to_string());
-    Ok(rendered_html?)
+    match game_template.filter(|path| path.is_file()) {
+        Some(path) => {
+            let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            let name = path.to_string_lossy().to_string();
+
+            // Clone so game templates can still extend/include the defaults
+            let mut tera = tera.clone();
+            tera.add_raw_template(&name, &source)
+                .map_err(|e| e.to_string())?;
+            tera.render(&name, &context).map_err(|e| e.to_string())
+        }
+        None => tera
+            .render(DEFAULT_TEMPLATE, &context)
+            .map_err(|e| e.to_string()),
+    }
 }
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+/*
+Lifecycle hooks a mudfile can define as duckscript functions:
+
+fn pre_install
+    echo checking the game version
+end
+
+Defining a function doesn't run it, so the rest of the file stays the install
+script. At the matching moment the runtime calls the hook, see hook_script.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Hook {
+    PreInstall,
+    PostInstall,
+    PreUninstall,
+    OnLaunch,
+}
+
+impl Hook {
+    pub fn function_name(&self) -> &'static str {
+        match self {
+            Hook::PreInstall => "pre_install",
+            Hook::PostInstall => "post_install",
+            Hook::PreUninstall => "pre_uninstall",
+            Hook::OnLaunch => "on_launch",
+        }
+    }
+}
+
+// Command name of a line, skipping a leading `:label` and an `out =` assignment
+fn command_name(line: &str) -> &str {
+    let mut words = line.split_whitespace().peekable();
+    if words.peek().map(|w| w.starts_with(':')).unwrap_or(false) {
+        words.next();
+    }
+    let words: Vec<&str> = words.take(3).collect();
+    match words[..] {
+        [_, "=", command, ..] => command,
+        [command, ..] => command,
+        [] => "",
+    }
+}
+
+fn opens_block(command: &str) -> bool {
+    matches!(command, "if" | "for" | "while" | "fn" | "function")
+}
+
+fn closes_block(command: &str) -> bool {
+    command == "end" || command.starts_with("end_")
+}
+
+// Name of the function a line starts, e.g. `fn pre_install`
+fn defined_function(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some("fn" | "function"), Some(name), None) => Some(name),
+        _ => None,
+    }
+}
+
+/*
+The script a hook runs: the whole mudfile with its top-level install steps
+blanked out and a call to the hook at the end. Function definitions and
+top-level assignments stay, so the hook can use the file's helpers and
+variables, and every line keeps its place for error reporting. None when the
+mudfile doesn't define the hook.
+*/
+pub fn hook_script(script: &str, hook: Hook) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut defined = false;
+    // Depth of the top-level block being walked, and whether it's a function
+    let mut depth = 0;
+    let mut in_function = false;
+    for line in script.lines() {
+        let command = command_name(line);
+        let keep = if depth > 0 {
+            if closes_block(command) {
+                depth -= 1;
+            } else if opens_block(command) {
+                depth += 1;
+            }
+            in_function
+        } else if matches!(command, "fn" | "function") {
+            defined |= defined_function(line) == Some(hook.function_name());
+            depth = 1;
+            in_function = true;
+            true
+        } else if opens_block(command) {
+            depth = 1;
+            in_function = false;
+            false
+        } else {
+            let words: Vec<&str> = line.split_whitespace().take(2).collect();
+            command.is_empty() || command.starts_with('#') || matches!(words[..], [_, "="])
+        };
+        lines.push(if keep { line } else { "" });
+    }
+    // A function that's never closed isn't a hook
+    if !defined || depth > 0 {
+        return None;
+    }
+    lines.push(hook.function_name());
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"mod_dir = set ${MUD_GAME_DIR}/Mods
+echo installing
+fn copy_mod
+    cp ${1} ${mod_dir}
+end
+if is_path_exists ${mod_dir}
+    rm -r ${mod_dir}
+end
+fn pre_install
+    if not is_path_exists ${mod_dir}
+        mkdir ${mod_dir}
+    end
+    copy_mod readme.txt
+end
+copy_mod mod.pak"#;
+
+    #[test]
+    fn hook_keeps_definitions_and_assignments() {
+        let script = hook_script(SCRIPT, Hook::PreInstall).unwrap();
+        let lines: Vec<&str> = script.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "mod_dir = set ${MUD_GAME_DIR}/Mods",
+                "",
+                "fn copy_mod",
+                "    cp ${1} ${mod_dir}",
+                "end",
+                "",
+                "",
+                "",
+                "fn pre_install",
+                "    if not is_path_exists ${mod_dir}",
+                "        mkdir ${mod_dir}",
+                "    end",
+                "    copy_mod readme.txt",
+                "end",
+                "",
+                "pre_install",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_or_unclosed_hooks_are_skipped() {
+        assert_eq!(hook_script(SCRIPT, Hook::PostInstall), None);
+        assert_eq!(
+            hook_script("fn pre_install\n    echo hi", Hook::PreInstall),
+            None
+        );
+        // Functions with arguments aren't hooks
+        assert_eq!(
+            hook_script("fn pre_install version\nend", Hook::PreInstall),
+            None
+        );
+    }
+}
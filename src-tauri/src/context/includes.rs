@@ -0,0 +1,283 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub static INCLUDE_DIRECTIVE: &str = "!include_files";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IncludeError {
+    Missing {
+        include: String,
+        included_from: Option<PathBuf>,
+        searched: Vec<PathBuf>,
+    },
+    Unreadable {
+        path: PathBuf,
+        message: String,
+    },
+    // The chain of files, ending with the one that's included again
+    Cycle(Vec<PathBuf>),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeError::Missing {
+                include,
+                included_from,
+                searched,
+            } => {
+                write!(f, "Included file '{}' was not found", include)?;
+                if let Some(from) = included_from {
+                    write!(f, " (included from {:?})", from)?;
+                }
+                if searched.is_empty() {
+                    return Ok(());
+                }
+                let searched: Vec<String> = searched.iter().map(|p| format!("{:?}", p)).collect();
+                write!(f, ", looked in {}", searched.join(", "))
+            }
+            IncludeError::Unreadable { path, message } => {
+                write!(f, "Failed to read included file {:?}: {}", path, message)
+            }
+            IncludeError::Cycle(chain) => {
+                let chain: Vec<String> = chain.iter().map(|p| format!("{:?}", p)).collect();
+                write!(f, "Include cycle: {}", chain.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+// Splits directive arguments on whitespace, keeping "quoted paths" together, \" and \\ are escapes
+fn split_args(args: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = args.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('"' | '\\')) => current.extend(chars.next()),
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+// Where a line of an expanded script came from, `file` is None for a script that wasn't read from one
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLine {
+    pub file: Option<PathBuf>,
+    pub line: usize,
+}
+
+// Original file and line of each line of an expanded script
+#[derive(Clone, Debug, Default)]
+pub struct LineMap {
+    lines: Vec<SourceLine>,
+}
+
+impl LineMap {
+    // `line` is 1-based, like duckscript's
+    pub fn source(&self, line: usize) -> Option<&SourceLine> {
+        line.checked_sub(1).and_then(|index| self.lines.get(index))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExpandedScript {
+    pub script: String,
+    pub lines: LineMap,
+}
+
+/*
+Inlines `!include_files` directives before a script runs. duckscript would
+resolve them against the process cwd, here a relative path is looked up next
+to the file that includes it first, then in each of the search paths, e.g. the
+game's mods folder and the community repo.
+*/
+pub struct IncludeResolver {
+    search_paths: Vec<PathBuf>,
+}
+
+impl IncludeResolver {
+    pub fn new(search_paths: Vec<PathBuf>) -> Self {
+        Self { search_paths }
+    }
+
+    // `source` is the file the script was read from, if any
+    pub fn expand(
+        &self,
+        script: &str,
+        source: Option<&Path>,
+    ) -> Result<ExpandedScript, IncludeError> {
+        let mut stack: Vec<PathBuf> = source
+            .map(|path| path.canonicalize().unwrap_or(path.to_path_buf()))
+            .into_iter()
+            .collect();
+        let mut lines = Vec::new();
+        let mut map = Vec::new();
+        self.expand_into(script, source, &mut stack, &mut lines, &mut map)?;
+        Ok(ExpandedScript {
+            script: lines.join("\n"),
+            lines: LineMap { lines: map },
+        })
+    }
+
+    fn expand_into(
+        &self,
+        script: &str,
+        source: Option<&Path>,
+        stack: &mut Vec<PathBuf>,
+        lines: &mut Vec<String>,
+        map: &mut Vec<SourceLine>,
+    ) -> Result<(), IncludeError> {
+        for (index, line) in script.lines().enumerate() {
+            let args = match line.trim_start().strip_prefix(INCLUDE_DIRECTIVE) {
+                Some(args) if args.is_empty() || args.starts_with(char::is_whitespace) => args,
+                _ => {
+                    lines.push(line.to_string());
+                    map.push(SourceLine {
+                        file: source.map(|s| s.to_path_buf()),
+                        line: index + 1,
+                    });
+                    continue;
+                }
+            };
+            for include in split_args(args) {
+                let path = self.locate(&include, source)?;
+                let canonical = path.canonicalize().unwrap_or(path.clone());
+                if stack.contains(&canonical) {
+                    let mut chain = stack.clone();
+                    chain.push(canonical);
+                    return Err(IncludeError::Cycle(chain));
+                }
+                let content =
+                    std::fs::read_to_string(&path).map_err(|e| IncludeError::Unreadable {
+                        path: path.clone(),
+                        message: e.to_string(),
+                    })?;
+                stack.push(canonical);
+                self.expand_into(&content, Some(&path), stack, lines, map)?;
+                stack.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn locate(&self, include: &str, source: Option<&Path>) -> Result<PathBuf, IncludeError> {
+        let include_path = Path::new(include);
+        if include_path.is_absolute() {
+            return match include_path.is_file() {
+                true => Ok(include_path.to_path_buf()),
+                false => Err(IncludeError::Missing {
+                    include: include.to_string(),
+                    included_from: source.map(|s| s.to_path_buf()),
+                    searched: Vec::new(),
+                }),
+            };
+        }
+
+        let mut searched = Vec::new();
+        let source_dir = source.and_then(|s| s.parent()).map(|d| d.to_path_buf());
+        for dir in source_dir.iter().chain(self.search_paths.iter()) {
+            let candidate = dir.join(include_path);
+            if candidate.is_file() {
+                return Ok(candidate);
+            }
+            searched.push(dir.clone());
+        }
+        Err(IncludeError::Missing {
+            include: include.to_string(),
+            included_from: source.map(|s| s.to_path_buf()),
+            searched,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::temp_dir;
+
+    #[test]
+    fn split_args_handles_quotes_and_escapes() {
+        assert_eq!(
+            split_args(r#" a.ds "with space.ds"  b.ds"#),
+            vec!["a.ds", "with space.ds", "b.ds"]
+        );
+        assert_eq!(
+            split_args(r#""say \"hi\".ds" back\\slash.ds"#),
+            vec![r#"say "hi".ds"#, r"back\slash.ds"]
+        );
+        // Other backslashes are kept, e.g. in Windows paths
+        assert_eq!(split_args(r"C:\mods\a.ds"), vec![r"C:\mods\a.ds"]);
+    }
+
+    #[test]
+    fn maps_expanded_lines_back_to_their_files() {
+        let dir = temp_dir("lines");
+        let main = dir.join("main.ds");
+        let shared = dir.join("lib").join("shared.ds");
+        let nested = dir.join("lib").join("nested.ds");
+        std::fs::create_dir_all(shared.parent().unwrap()).unwrap();
+        std::fs::write(
+            &shared,
+            "echo shared 1\n!include_files nested.ds\necho shared 3",
+        )
+        .unwrap();
+        std::fs::write(&nested, "echo nested 1").unwrap();
+        let script = "echo main 1\n!include_files lib/shared.ds\necho main 3";
+
+        let expanded = IncludeResolver::new(Vec::new())
+            .expand(script, Some(&main))
+            .unwrap();
+        assert_eq!(
+            expanded.script,
+            "echo main 1\necho shared 1\necho nested 1\necho shared 3\necho main 3"
+        );
+        let source = |line| expanded.lines.source(line).cloned().unwrap();
+        let at = |file: &Path, line| SourceLine {
+            file: Some(file.to_path_buf()),
+            line,
+        };
+        assert_eq!(source(1), at(&main, 1));
+        assert_eq!(source(2), at(&shared, 1));
+        assert_eq!(source(3), at(&nested, 1));
+        assert_eq!(source(4), at(&shared, 3));
+        assert_eq!(source(5), at(&main, 3));
+        assert_eq!(expanded.lines.source(6), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_include_cycles() {
+        let dir = temp_dir("cycle");
+        let a = dir.join("a.ds");
+        let b = dir.join("b.ds");
+        std::fs::write(&a, "!include_files b.ds").unwrap();
+        std::fs::write(&b, "!include_files a.ds").unwrap();
+
+        let error = IncludeResolver::new(Vec::new())
+            .expand("!include_files a.ds", Some(&dir.join("main.ds")))
+            .unwrap_err();
+        let a = a.canonicalize().unwrap();
+        let b = b.canonicalize().unwrap();
+        match error {
+            IncludeError::Cycle(chain) => assert_eq!(chain[1..], [a.clone(), b, a]),
+            error => panic!("expected a cycle, got {}", error),
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
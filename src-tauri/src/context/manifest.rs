@@ -0,0 +1,115 @@
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// Header lines look like `# mud:<key> <value>`, so plain duckscript treats them as comments
+pub static MANIFEST_PREFIX: &str = "# mud:";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModDependency {
+    pub id: String,
+    pub req: VersionReq,
+}
+
+impl ModDependency {
+    // Parses `<id> [<semver requirement>]`, an omitted requirement matches any version
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (id, req) = match value.trim().split_once(char::is_whitespace) {
+            Some((id, req)) => (id, req.trim()),
+            None => (value.trim(), "*"),
+        };
+        if id.is_empty() {
+            return Err("dependency is missing a mod id".to_string());
+        }
+        let req = VersionReq::parse(req)
+            .map_err(|e| format!("invalid version requirement '{}' for '{}': {}", req, id, e))?;
+        Ok(Self {
+            id: id.to_string(),
+            req,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModManifest {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<Version>,
+    pub description: Option<String>,
+    pub dependencies: Vec<ModDependency>,
+    pub conflicts: Vec<ModDependency>,
+    // Load order hints, by mod id
+    pub load_after: Vec<String>,
+    pub load_before: Vec<String>,
+    // Any other keys, e.g. game specific values used by load order writers
+    pub extra: BTreeMap<String, String>,
+    pub path: Option<PathBuf>,
+}
+
+impl ModManifest {
+    pub fn version_or_default(&self) -> Version {
+        self.version.clone().unwrap_or(Version::new(0, 0, 0))
+    }
+
+    pub fn display_name(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.id, version),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/*
+Reads the manifest header from the top of a mudfile. Parsing stops at the
+first line that isn't blank or a comment. Returns None if there's no `id`.
+*/
+pub fn parse_manifest(script: &str) -> Result<Option<ModManifest>, String> {
+    let mut manifest = ModManifest::default();
+
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('#') {
+            break;
+        }
+        let entry = match line.strip_prefix(MANIFEST_PREFIX) {
+            Some(entry) => entry,
+            None => continue,
+        };
+
+        let (key, value) = entry.split_once(char::is_whitespace).unwrap_or((entry, ""));
+        let value = value.trim();
+        let error = |e: String| format!("Manifest error on line {}: {}", index + 1, e);
+        match key {
+            "id" => manifest.id = value.to_string(),
+            "name" => manifest.name = Some(value.to_string()),
+            "description" => manifest.description = Some(value.to_string()),
+            "version" => {
+                let version = Version::parse(value)
+                    .map_err(|e| error(format!("invalid version '{}': {}", value, e)))?;
+                manifest.version = Some(version);
+            }
+            "depends" => manifest
+                .dependencies
+                .push(ModDependency::parse(value).map_err(error)?),
+            "conflicts" => manifest
+                .conflicts
+                .push(ModDependency::parse(value).map_err(error)?),
+            "after" => manifest.load_after.push(value.to_string()),
+            "before" => manifest.load_before.push(value.to_string()),
+            _ => {
+                manifest.extra.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+
+    if manifest.id.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(manifest))
+    }
+}
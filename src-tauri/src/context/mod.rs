@@ -1,7 +1,12 @@
+pub mod environment;
 pub mod forms;
+pub mod hooks;
+pub mod includes;
+pub mod manifest;
 pub mod parser;
 
 use duckscript::types::runtime::Context;
+use serde_json::{Map, Value};
 
 // Helper function to set up context with arguments
 #[allow(dead_code)]
@@ -12,3 +17,14 @@ pub fn setup_context_with_args(context: &mut Context, args: Vec<String>) {
             .insert(format!("arg{}", index), arg.clone());
     }
 }
+
+// Exposes a profile's saved settings to scripts as ${setting_<key>}
+pub fn setup_context_with_settings(context: &mut Context, settings: &Map<String, Value>) {
+    for (key, value) in settings {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        context.variables.insert(format!("setting_{}", key), value);
+    }
+}
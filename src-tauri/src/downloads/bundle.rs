@@ -0,0 +1,116 @@
+use crate::downloads::cache::DownloadCache;
+use crate::mods::unix_now;
+use crate::utils::error_handler::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub static BUNDLE_MANIFEST: &str = "bundle.json";
+pub static BUNDLE_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleEntry {
+    pub url: String,
+    pub sha256: String,
+    pub size: u64,
+    // Mods whose mudfiles download this file
+    pub mods: Vec<String>,
+}
+
+// Downloads found during discovery that can't be bundled, e.g. `wget` without a checksum
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedDownload {
+    pub url: String,
+    pub mod_id: String,
+    pub reason: String,
+}
+
+/*
+A folder of downloads that can be carried to another machine:
+bundle.json   this manifest
+cache/<sha>   the files, laid out like the download cache itself
+*/
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadBundle {
+    pub version: u32,
+    pub created_at: u64,
+    pub entries: Vec<BundleEntry>,
+    #[serde(default)]
+    pub skipped: Vec<SkippedDownload>,
+}
+
+impl DownloadBundle {
+    pub fn new() -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            created_at: unix_now(),
+            ..Default::default()
+        }
+    }
+
+    // Missing bundles load as empty so prefetching can add to an existing folder
+    pub fn load(dir: &Path) -> Result<Self, AppError> {
+        let path = dir.join(BUNDLE_MANIFEST);
+        if !path.is_file() {
+            return Ok(Self::new());
+        }
+        let bundle: Self = serde_json::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|e| AppError::ParsingError(e.to_string()))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(AppError::ParsingError(format!(
+                "Unsupported download bundle version {}",
+                bundle.version
+            )));
+        }
+        Ok(bundle)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), AppError> {
+        std::fs::create_dir_all(dir)?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::ParsingError(e.to_string()))?;
+        std::fs::write(dir.join(BUNDLE_MANIFEST), content)?;
+        Ok(())
+    }
+
+    pub fn cache(dir: &Path) -> DownloadCache {
+        DownloadCache::at(dir.to_path_buf())
+    }
+
+    pub fn add(&mut self, url: &str, sha256: &str, size: u64, mod_id: &str) {
+        match self.entries.iter_mut().find(|e| e.sha256 == sha256) {
+            Some(entry) => {
+                if !entry.mods.iter().any(|m| m == mod_id) {
+                    entry.mods.push(mod_id.to_string());
+                }
+            }
+            None => self.entries.push(BundleEntry {
+                url: url.to_string(),
+                sha256: sha256.to_string(),
+                size,
+                mods: vec![mod_id.to_string()],
+            }),
+        }
+    }
+
+    // Copies every bundled file into `cache`, returning how many were imported
+    pub fn import_into(&self, dir: &Path, cache: &DownloadCache) -> Result<usize, AppError> {
+        let bundled = Self::cache(dir);
+        let mut imported = 0;
+        for entry in &self.entries {
+            let source = bundled.get(&entry.sha256).ok_or_else(|| {
+                AppError::DownloadError(format!("{} is missing from the bundle", entry.url))
+            })?;
+            if !cache.import(&entry.sha256, &source)? {
+                return Err(AppError::DownloadError(format!(
+                    "Bundled file for {} doesn't match its checksum",
+                    entry.url
+                )));
+            }
+            imported += 1;
+        }
+        Ok(imported)
+    }
+}
@@ -0,0 +1,83 @@
+use crate::utils::files::copy_file;
+use crate::utils::hashing::hash_file;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub static DOWNLOADS_DIR: &str = "downloads";
+
+/*
+Downloaded files keyed by their sha256, under <appdata>/downloads:
+cache/<sha256>         verified, complete files
+partial/<sha256>.part  interrupted downloads, resumed on the next attempt
+*/
+#[derive(Clone, Debug)]
+pub struct DownloadCache {
+    root: PathBuf,
+}
+
+impl DownloadCache {
+    pub fn new(app_data: &Path) -> Self {
+        Self::at(app_data.join(DOWNLOADS_DIR))
+    }
+
+    // Cache rooted at an arbitrary folder
+    pub fn at(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn cached_path(&self, sha256: &str) -> PathBuf {
+        self.root.join("cache").join(sha256)
+    }
+
+    pub fn partial_path(&self, sha256: &str) -> PathBuf {
+        self.root.join("partial").join(format!("{}.part", sha256))
+    }
+
+    // Only files that passed verification are ever moved into the cache
+    pub fn get(&self, sha256: &str) -> Option<PathBuf> {
+        let path = self.cached_path(sha256);
+        path.is_file().then_some(path)
+    }
+
+    // Moves a verified partial download into the cache
+    pub fn insert(&self, sha256: &str, partial: &Path) -> io::Result<PathBuf> {
+        let path = self.cached_path(sha256);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(partial, &path)?;
+        Ok(path)
+    }
+
+    // Copies a file into the cache if it matches `sha256`, returns false when it doesn't
+    pub fn import(&self, sha256: &str, source: &Path) -> io::Result<bool> {
+        if self.get(sha256).is_some() {
+            return Ok(true);
+        }
+        if hash_file(source)? != sha256 {
+            return Ok(false);
+        }
+        let partial = self.partial_path(sha256);
+        copy_file(source, &partial)?;
+        self.insert(sha256, &partial)?;
+        Ok(true)
+    }
+
+    // Deletes every cached and partial file, returning the bytes freed
+    pub fn clear(&self) -> io::Result<u64> {
+        let mut freed = 0;
+        for dir in [self.root.join("cache"), self.root.join("partial")] {
+            for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+                freed += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(freed)
+    }
+}
+
+// Checksums are compared lowercase, and must be a full sha256
+pub fn normalize_sha256(sha256: &str) -> Option<String> {
+    let sha256 = sha256.trim().to_lowercase();
+    (sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit())).then_some(sha256)
+}
@@ -0,0 +1,498 @@
+use crate::downloads::cache::{normalize_sha256, DownloadCache};
+use crate::utils::error_handler::AppError;
+use crate::utils::files::copy_file;
+use crate::utils::hashing::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::Duration;
+
+pub static MAX_CONCURRENT_DOWNLOADS: usize = 4;
+
+// Progress is reported at most once per this many bytes, plus once at the end
+const PROGRESS_INTERVAL: u64 = 256 * 1024;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadRequest {
+    pub url: String,
+    pub sha256: String,
+    // Copied here from the cache when set
+    pub destination: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub url: String,
+    pub sha256: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    // Picked up from an earlier interrupted download
+    pub resumed: bool,
+    pub cached: bool,
+    pub done: bool,
+}
+
+pub type ProgressSink = Arc<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+/*
+Caps how many downloads run at once, and keeps two downloads of the same
+file from writing the same partial file.
+*/
+#[derive(Default)]
+pub struct DownloadLimiter {
+    max: usize,
+    state: Mutex<(usize, HashSet<String>)>,
+    changed: Condvar,
+}
+
+pub struct LimiterGuard<'a> {
+    limiter: &'a DownloadLimiter,
+    sha256: String,
+}
+
+impl DownloadLimiter {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max: max.max(1),
+            ..Default::default()
+        }
+    }
+
+    pub fn acquire(&self, sha256: &str) -> LimiterGuard<'_> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while state.0 >= self.max || state.1.contains(sha256) {
+            state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.0 += 1;
+        state.1.insert(sha256.to_string());
+        LimiterGuard {
+            limiter: self,
+            sha256: sha256.to_string(),
+        }
+    }
+}
+
+impl Drop for LimiterGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.0 -= 1;
+        state.1.remove(&self.sha256);
+        self.limiter.changed.notify_all();
+    }
+}
+
+// Shared by every manager in the app so the limit holds across script runs
+fn shared_limiter() -> Arc<DownloadLimiter> {
+    static LIMITER: OnceLock<Arc<DownloadLimiter>> = OnceLock::new();
+    LIMITER
+        .get_or_init(|| Arc::new(DownloadLimiter::new(MAX_CONCURRENT_DOWNLOADS)))
+        .clone()
+}
+
+// Start offset and total size from a `Content-Range: bytes <start>-<end>/<total>` header
+fn parse_content_range(header: &str) -> Option<(u64, Option<u64>)> {
+    let range = header.trim().strip_prefix("bytes ")?;
+    let (span, total) = range.split_once('/')?;
+    let start = span.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+#[derive(Clone)]
+pub struct DownloadManager {
+    cache: DownloadCache,
+    agent: ureq::Agent,
+    limiter: Arc<DownloadLimiter>,
+    progress: Option<ProgressSink>,
+    // Only serve files that are already cached
+    offline: bool,
+}
+
+impl DownloadManager {
+    pub fn new(cache: DownloadCache) -> Self {
+        Self::with_limiter(cache, shared_limiter())
+    }
+
+    // Separate limiter, e.g. for a manager pointed at a local test server
+    pub fn with_limiter(cache: DownloadCache, limiter: Arc<DownloadLimiter>) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(30))
+            .timeout_read(Duration::from_secs(60))
+            .build();
+        Self {
+            cache,
+            agent,
+            limiter,
+            progress: None,
+            offline: false,
+        }
+    }
+
+    pub fn with_progress(mut self, progress: ProgressSink) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    fn report(&self, progress: DownloadProgress) {
+        if let Some(sink) = &self.progress {
+            sink(&progress);
+        }
+    }
+
+    /*
+    Returns the verified file, from the cache when possible. Interrupted
+    downloads resume with a range request, and a checksum mismatch discards
+    the partial file so the next attempt starts over.
+    */
+    pub fn fetch(&self, request: &DownloadRequest) -> Result<PathBuf, AppError> {
+        let sha256 = normalize_sha256(&request.sha256).ok_or_else(|| {
+            AppError::DownloadError(format!(
+                "'{}' is not a valid sha256 for {}",
+                request.sha256, request.url
+            ))
+        })?;
+
+        let (cached, from_cache) = match self.cache.get(&sha256) {
+            Some(cached) => (cached, true),
+            None if self.offline => {
+                return Err(AppError::DownloadError(format!(
+                    "{} isn't in the download cache and offline mode is on",
+                    request.url
+                )))
+            }
+            None => {
+                let _guard = self.limiter.acquire(&sha256);
+                // Someone else may have finished it while we waited
+                match self.cache.get(&sha256) {
+                    Some(cached) => (cached, true),
+                    None => (self.download(&request.url, &sha256)?, false),
+                }
+            }
+        };
+
+        let size = cached.metadata().map(|m| m.len()).unwrap_or(0);
+        self.report(DownloadProgress {
+            url: request.url.clone(),
+            sha256: sha256.clone(),
+            downloaded: size,
+            total: Some(size),
+            resumed: false,
+            cached: from_cache,
+            done: true,
+        });
+
+        match &request.destination {
+            Some(destination) => {
+                copy_file(&cached, destination)?;
+                Ok(destination.clone())
+            }
+            None => Ok(cached),
+        }
+    }
+
+    // Fetches several files at once, up to the limiter's cap
+    pub fn fetch_all(&self, requests: &[DownloadRequest]) -> Vec<Result<PathBuf, AppError>> {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = requests
+                .iter()
+                .map(|request| scope.spawn(move || self.fetch(request)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle.join().unwrap_or_else(|_| {
+                        Err(AppError::DownloadError(
+                            "Download thread panicked".to_string(),
+                        ))
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn download(&self, url: &str, sha256: &str) -> Result<PathBuf, AppError> {
+        let partial = self.cache.partial_path(sha256);
+        if let Some(parent) = partial.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let offset = partial.metadata().map(|m| m.len()).unwrap_or(0);
+
+        let mut call = self.agent.get(url);
+        if offset > 0 {
+            call = call.set("Range", &format!("bytes={}-", offset));
+        }
+        let response = match call.call() {
+            Ok(response) => Some(response),
+            // The partial file already has every byte
+            Err(ureq::Error::Status(416, _)) if offset > 0 => None,
+            Err(e) => return Err(AppError::DownloadError(format!("{}: {}", url, e))),
+        };
+
+        let mut progress = DownloadProgress {
+            url: url.to_string(),
+            sha256: sha256.to_string(),
+            downloaded: offset,
+            total: None,
+            resumed: false,
+            cached: false,
+            done: false,
+        };
+        if let Some(response) = response {
+            let range = response
+                .header("Content-Range")
+                .and_then(parse_content_range);
+            let resumed = response.status() == 206 && range.map(|(start, _)| start) == Some(offset);
+            let length: Option<u64> = response
+                .header("Content-Length")
+                .and_then(|l| l.trim().parse().ok());
+
+            // Servers that ignore the range send the whole file again
+            let file = if resumed {
+                OpenOptions::new().append(true).open(&partial)?
+            } else {
+                File::create(&partial)?
+            };
+            progress.resumed = resumed;
+            progress.downloaded = if resumed { offset } else { 0 };
+            progress.total = match (resumed, range) {
+                (true, Some((_, total))) => total,
+                (true, None) => length.map(|l| l + offset),
+                (false, _) => length,
+            };
+            self.write_body(response.into_reader(), file, &mut progress)?;
+        }
+
+        let actual = hash_file(&partial)?;
+        if actual != sha256 {
+            std::fs::remove_file(&partial)?;
+            return Err(AppError::DownloadError(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                url, sha256, actual
+            )));
+        }
+        Ok(self.cache.insert(sha256, &partial)?)
+    }
+
+    fn write_body(
+        &self,
+        mut reader: impl Read,
+        mut file: File,
+        progress: &mut DownloadProgress,
+    ) -> io::Result<()> {
+        let mut buffer = vec![0; 64 * 1024];
+        let mut last_report = progress.downloaded;
+        self.report(progress.clone());
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])?;
+            progress.downloaded += read as u64;
+            if progress.downloaded - last_report >= PROGRESS_INTERVAL {
+                last_report = progress.downloaded;
+                self.report(progress.clone());
+            }
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hashing::hash_bytes;
+    use crate::utils::testing::temp_dir;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    static BODY: &[u8] = b"a mod archive that takes two tries";
+
+    // Range header of each request the server got, None when it had none
+    type Requests = Arc<Mutex<Vec<Option<String>>>>;
+
+    /*
+    Answers `connections` requests with the raw HTTP response `respond` builds
+    from the request's Range header, then stops listening.
+    */
+    fn serve<F>(connections: usize, respond: F) -> (String, Requests)
+    where
+        F: Fn(Option<&str>) -> Vec<u8> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/mod.zip", listener.local_addr().unwrap());
+        let requests: Requests = Arc::default();
+        let seen = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                let mut range = None;
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("range") {
+                            range = Some(value.trim().to_string());
+                        }
+                    }
+                }
+                stream.write_all(&respond(range.as_deref())).unwrap();
+                seen.lock().unwrap().push(range);
+            }
+        });
+        (url, requests)
+    }
+
+    fn response(status: &str, headers: &[String], body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        for header in headers {
+            response.push_str(&format!("{}\r\n", header));
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    // A manager with its own cache folder and limiter, the folder is removed by the caller
+    fn manager(name: &str) -> (DownloadManager, PathBuf) {
+        let root = temp_dir(name);
+        let manager = DownloadManager::with_limiter(
+            DownloadCache::at(root.clone()),
+            Arc::new(DownloadLimiter::new(1)),
+        );
+        (manager, root)
+    }
+
+    fn request(url: &str, sha256: &str) -> DownloadRequest {
+        DownloadRequest {
+            url: url.to_string(),
+            sha256: sha256.to_string(),
+            destination: None,
+        }
+    }
+
+    #[test]
+    fn resumes_a_truncated_download_with_a_range_request() {
+        let cut = 10;
+        let (url, requests) = serve(2, move |range| match range {
+            // Promises the whole body but hangs up part way through
+            None => response(
+                "200 OK",
+                &[format!("Content-Length: {}", BODY.len())],
+                &BODY[..cut],
+            ),
+            Some(_) => response(
+                "206 Partial Content",
+                &[
+                    format!("Content-Length: {}", BODY.len() - cut),
+                    format!(
+                        "Content-Range: bytes {}-{}/{}",
+                        cut,
+                        BODY.len() - 1,
+                        BODY.len()
+                    ),
+                ],
+                &BODY[cut..],
+            ),
+        });
+        let (manager, root) = manager("resume");
+        let sha256 = hash_bytes(BODY);
+
+        assert!(manager.fetch(&request(&url, &sha256)).is_err());
+        let partial = manager.cache.partial_path(&sha256);
+        assert_eq!(std::fs::read(&partial).unwrap(), &BODY[..cut]);
+
+        let fetched = manager.fetch(&request(&url, &sha256)).unwrap();
+        assert_eq!(std::fs::read(&fetched).unwrap(), BODY);
+        assert!(!partial.exists());
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![None, Some(format!("bytes={}-", cut))]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn finishes_a_complete_partial_on_416() {
+        let (url, requests) = serve(1, |_| {
+            response(
+                "416 Range Not Satisfiable",
+                &["Content-Length: 0".to_string()],
+                b"",
+            )
+        });
+        let (manager, root) = manager("416");
+        let sha256 = hash_bytes(BODY);
+        let partial = manager.cache.partial_path(&sha256);
+        std::fs::create_dir_all(partial.parent().unwrap()).unwrap();
+        std::fs::write(&partial, BODY).unwrap();
+
+        let fetched = manager.fetch(&request(&url, &sha256)).unwrap();
+        assert_eq!(fetched, manager.cache.cached_path(&sha256));
+        assert_eq!(std::fs::read(&fetched).unwrap(), BODY);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![Some(format!("bytes={}-", BODY.len()))]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn checksum_mismatch_discards_the_partial() {
+        let (url, _) = serve(1, |_| {
+            response("200 OK", &[format!("Content-Length: {}", BODY.len())], BODY)
+        });
+        let (manager, root) = manager("mismatch");
+        let sha256 = hash_bytes(b"some other file");
+
+        let error = manager.fetch(&request(&url, &sha256)).unwrap_err();
+        assert!(error.to_string().contains("Checksum mismatch"), "{}", error);
+        assert!(!manager.cache.partial_path(&sha256).exists());
+        assert!(manager.cache.get(&sha256).is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn cache_hit_skips_the_network() {
+        // Nothing listens here, so any request would fail
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/mod.zip", listener.local_addr().unwrap())
+        };
+        let (manager, root) = manager("cached");
+        let sha256 = hash_bytes(BODY);
+        let source = root.join("mod.zip");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(&source, BODY).unwrap();
+        assert!(manager.cache.import(&sha256, &source).unwrap());
+
+        let reports: Arc<Mutex<Vec<DownloadProgress>>> = Arc::default();
+        let sink = reports.clone();
+        let manager =
+            manager.with_progress(Arc::new(move |p| sink.lock().unwrap().push(p.clone())));
+        let fetched = manager.fetch(&request(&url, &sha256)).unwrap();
+        assert_eq!(fetched, manager.cache.cached_path(&sha256));
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].cached && reports[0].done);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,7 @@
+pub mod bundle;
+pub mod cache;
+pub mod manager;
+
+pub use bundle::*;
+pub use cache::*;
+pub use manager::*;
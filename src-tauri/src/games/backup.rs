@@ -0,0 +1,270 @@
+use crate::games::snapshot::SnapshotFile;
+use crate::mods::ledger::unix_now;
+use crate::utils::error_handler::AppError;
+use crate::utils::files::{copy_file, walk_files};
+use crate::utils::hashing::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub static BACKUPS_DIR: &str = "backups";
+
+/*
+The "backup" section of a game's config.json, e.g.
+"backup": {
+  "paths": {
+    "saves": "~/.local/share/Larian Studios/Baldur's Gate 3/PlayerProfiles/Public/Savegames",
+    "config": "~/.local/share/Larian Studios/Baldur's Gate 3/PlayerProfiles/Public"
+  },
+  "keep": 10
+}
+*/
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupRules {
+    #[serde(default)]
+    pub paths: BTreeMap<String, String>,
+    // How many backups survive the prune after each automatic one
+    pub keep: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupDir {
+    pub name: String,
+    pub path: PathBuf,
+    pub files: BTreeMap<PathBuf, SnapshotFile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    pub id: String,
+    pub created_at: u64,
+    pub reason: Option<String>,
+    pub dirs: Vec<BackupDir>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub id: String,
+    pub created_at: u64,
+    pub reason: Option<String>,
+    pub dirs: Vec<String>,
+    pub file_count: usize,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneReport {
+    pub removed: Vec<String>,
+    // Bytes of file contents no remaining backup used
+    pub freed: u64,
+}
+
+impl Backup {
+    pub fn info(&self) -> BackupInfo {
+        BackupInfo {
+            id: self.id.clone(),
+            created_at: self.created_at,
+            reason: self.reason.clone(),
+            dirs: self.dirs.iter().map(|d| d.name.clone()).collect(),
+            file_count: self.dirs.iter().map(|d| d.files.len()).sum(),
+            size: self
+                .dirs
+                .iter()
+                .flat_map(|d| d.files.values())
+                .map(|f| f.size)
+                .sum(),
+        }
+    }
+}
+
+/*
+Backups of one game, under <appdata>/backups/<game>:
+<id>.json      which files each backed up folder had
+objects/<sha>  file contents, shared between backups so unchanged files are stored once
+*/
+pub struct BackupStore {
+    root: PathBuf,
+}
+
+impl BackupStore {
+    pub fn new(app_data: &Path, game_id: &str) -> Self {
+        Self {
+            root: app_data.join(BACKUPS_DIR).join(game_id),
+        }
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join("objects").join(hash)
+    }
+
+    fn manifest_path(&self, id: &str) -> PathBuf {
+        self.root.join(format!("{}.json", id))
+    }
+
+    // Timestamp ids, with a suffix when two backups land in the same second
+    fn next_id(&self, created_at: u64) -> String {
+        let mut id = created_at.to_string();
+        let mut suffix = 1;
+        while self.manifest_path(&id).exists() {
+            id = format!("{}-{}", created_at, suffix);
+            suffix += 1;
+        }
+        id
+    }
+
+    // Copies every file under the named folders into the store, skipping folders that don't exist
+    pub fn create(
+        &self,
+        dirs: &BTreeMap<String, PathBuf>,
+        reason: Option<String>,
+    ) -> Result<Backup, AppError> {
+        let mut backup_dirs = Vec::new();
+        for (name, dir) in dirs {
+            if !dir.is_dir() {
+                continue;
+            }
+            let mut files = BTreeMap::new();
+            for file in walk_files(dir) {
+                let relative = match file.strip_prefix(dir) {
+                    Ok(relative) => relative.to_path_buf(),
+                    Err(_) => continue,
+                };
+                let hash = hash_file(&file)?;
+                let object = self.object_path(&hash);
+                if !object.exists() {
+                    copy_file(&file, &object)?;
+                }
+                let size = file.metadata()?.len();
+                files.insert(relative, SnapshotFile { hash, size });
+            }
+            backup_dirs.push(BackupDir {
+                name: name.clone(),
+                path: dir.clone(),
+                files,
+            });
+        }
+
+        let created_at = unix_now();
+        let backup = Backup {
+            id: self.next_id(created_at),
+            created_at,
+            reason,
+            dirs: backup_dirs,
+        };
+        let content =
+            serde_json::to_string(&backup).map_err(|e| AppError::ParsingError(e.to_string()))?;
+        std::fs::create_dir_all(&self.root)?;
+        std::fs::write(self.manifest_path(&backup.id), content)?;
+        Ok(backup)
+    }
+
+    pub fn load(&self, id: &str) -> Result<Backup, AppError> {
+        let path = self.manifest_path(id);
+        if !path.is_file() {
+            return Err(AppError::BackupError(format!(
+                "Backup '{}' does not exist",
+                id
+            )));
+        }
+        serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| AppError::ParsingError(e.to_string()))
+    }
+
+    // Every backup, newest first
+    pub fn list(&self) -> Result<Vec<Backup>, AppError> {
+        let mut backups = Vec::new();
+        for entry in std::fs::read_dir(&self.root).into_iter().flatten() {
+            let path = entry?.path();
+            if path.extension().map(|e| e != "json").unwrap_or(true) {
+                continue;
+            }
+            match std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<Backup>(&content).ok())
+            {
+                Some(backup) => backups.push(backup),
+                None => eprintln!("Skipping unreadable backup {:?}", path),
+            }
+        }
+        backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+        Ok(backups)
+    }
+
+    /*
+    Puts the backed up folders back the way they were, removing files that
+    were added since. `names` limits the restore to some of the folders.
+    */
+    pub fn restore(&self, id: &str, names: Option<&[String]>) -> Result<Backup, AppError> {
+        let backup = self.load(id)?;
+        for dir in &backup.dirs {
+            if names.map(|n| !n.contains(&dir.name)).unwrap_or(false) {
+                continue;
+            }
+            for (relative, file) in &dir.files {
+                let object = self.object_path(&file.hash);
+                if !object.is_file() {
+                    return Err(AppError::BackupError(format!(
+                        "Backup '{}' is missing the contents of {:?}",
+                        id, relative
+                    )));
+                }
+            }
+            for file in walk_files(&dir.path) {
+                let known = file
+                    .strip_prefix(&dir.path)
+                    .map(|relative| dir.files.contains_key(relative))
+                    .unwrap_or(true);
+                if !known {
+                    std::fs::remove_file(&file)?;
+                }
+            }
+            for (relative, file) in &dir.files {
+                let target = dir.path.join(relative);
+                let unchanged = target
+                    .metadata()
+                    .map(|m| m.len() == file.size)
+                    .unwrap_or(false)
+                    && hash_file(&target).map(|h| h == file.hash).unwrap_or(false);
+                if !unchanged {
+                    copy_file(&self.object_path(&file.hash), &target)?;
+                }
+            }
+        }
+        Ok(backup)
+    }
+
+    // Keeps the newest `keep` backups and drops contents nothing refers to anymore
+    pub fn prune(&self, keep: usize) -> Result<PruneReport, AppError> {
+        let backups = self.list()?;
+        let mut report = PruneReport::default();
+        for backup in backups.iter().skip(keep) {
+            std::fs::remove_file(self.manifest_path(&backup.id))?;
+            report.removed.push(backup.id.clone());
+        }
+
+        let referenced: HashSet<&String> = backups
+            .iter()
+            .take(keep)
+            .flat_map(|b| b.dirs.iter())
+            .flat_map(|d| d.files.values())
+            .map(|f| &f.hash)
+            .collect();
+        for entry in std::fs::read_dir(self.root.join("objects"))
+            .into_iter()
+            .flatten()
+        {
+            let entry = entry?;
+            let hash = entry.file_name().to_string_lossy().to_string();
+            if !referenced.contains(&hash) {
+                report.freed += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,172 @@
+use crate::games::steam::{find_app_dir, steam_libraries};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/*
+The "detect" section of a game's config.json, e.g.
+"detect": {
+  "steamAppId": "1086940",
+  "steamFolder": "Baldurs Gate 3",
+  "paths": ["~/Games/Baldurs Gate 3"],
+  "markers": ["Data/Gustav.pak"],
+  "executables": ["bg3.exe", "bg3_dx11.exe"]
+}
+*/
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectionRules {
+    pub steam_app_id: Option<String>,
+    // Folder name under steamapps/common, used when there's no app manifest
+    pub steam_folder: Option<String>,
+    #[serde(default)]
+    pub paths: Vec<String>,
+    // Files relative to the install dir that only this game has
+    #[serde(default)]
+    pub markers: Vec<String>,
+    #[serde(default)]
+    pub executables: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DetectionSource {
+    SteamManifest,
+    SteamLibrary,
+    KnownPath,
+}
+
+impl DetectionSource {
+    fn base_confidence(&self) -> f32 {
+        match self {
+            DetectionSource::SteamManifest => 0.5,
+            DetectionSource::SteamLibrary => 0.3,
+            DetectionSource::KnownPath => 0.2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectedGame {
+    pub game_id: String,
+    pub path: PathBuf,
+    pub source: DetectionSource,
+    // 0 to 1, higher when more rules match
+    pub confidence: f32,
+    // Markers and executables found in the candidate dir
+    pub matched: Vec<String>,
+}
+
+// Executables are usually at the root or a few folders down, e.g. bin/ or Binaries/Win64/
+const EXECUTABLE_SEARCH_DEPTH: usize = 4;
+
+pub fn expand_home(path: &str, home: &Path) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+fn find_executable(dir: &Path, names: &[String]) -> Option<String> {
+    WalkDir::new(dir)
+        .max_depth(EXECUTABLE_SEARCH_DEPTH)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .find_map(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            names
+                .iter()
+                .find(|n| n.eq_ignore_ascii_case(&name))
+                .map(|_| {
+                    entry
+                        .path()
+                        .strip_prefix(dir)
+                        .unwrap_or(entry.path())
+                        .to_string_lossy()
+                        .to_string()
+                })
+        })
+}
+
+// Scores a candidate dir against the markers and executables, None if it doesn't exist
+fn evaluate(
+    game_id: &str,
+    path: PathBuf,
+    source: DetectionSource,
+    rules: &DetectionRules,
+) -> Option<DetectedGame> {
+    if !path.is_dir() {
+        return None;
+    }
+
+    let mut matched = Vec::new();
+    let mut confidence = source.base_confidence();
+
+    if !rules.markers.is_empty() {
+        let found: Vec<&String> = rules
+            .markers
+            .iter()
+            .filter(|m| path.join(m).exists())
+            .collect();
+        confidence += 0.3 * found.len() as f32 / rules.markers.len() as f32;
+        matched.extend(found.into_iter().cloned());
+    }
+    if !rules.executables.is_empty() {
+        if let Some(executable) = find_executable(&path, &rules.executables) {
+            confidence += 0.2;
+            matched.push(executable);
+        }
+    }
+
+    Some(DetectedGame {
+        game_id: game_id.to_string(),
+        path,
+        source,
+        confidence: confidence.min(1.0),
+        matched,
+    })
+}
+
+// Candidate install dirs for one game, best match first
+pub fn detect_game(game_id: &str, rules: &DetectionRules, home: &Path) -> Vec<DetectedGame> {
+    let mut candidates: Vec<(PathBuf, DetectionSource)> = Vec::new();
+
+    if rules.steam_app_id.is_some() || rules.steam_folder.is_some() {
+        let libraries = steam_libraries(home);
+        if let Some(app_id) = &rules.steam_app_id {
+            if let Some(dir) = find_app_dir(&libraries, app_id) {
+                candidates.push((dir, DetectionSource::SteamManifest));
+            }
+        }
+        // Duplicates of the manifest's folder are dropped below
+        if let Some(folder) = &rules.steam_folder {
+            for library in &libraries {
+                candidates.push((
+                    library.steamapps().join("common").join(folder),
+                    DetectionSource::SteamLibrary,
+                ));
+            }
+        }
+    }
+    for path in &rules.paths {
+        candidates.push((expand_home(path, home), DetectionSource::KnownPath));
+    }
+
+    let mut detected: Vec<DetectedGame> = Vec::new();
+    for (path, source) in candidates {
+        let canonical = path.canonicalize().unwrap_or(path.clone());
+        if detected
+            .iter()
+            .any(|d| d.path.canonicalize().unwrap_or(d.path.clone()) == canonical)
+        {
+            continue;
+        }
+        if let Some(game) = evaluate(game_id, path, source, rules) {
+            detected.push(game);
+        }
+    }
+    detected.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    detected
+}
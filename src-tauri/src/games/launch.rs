@@ -0,0 +1,201 @@
+use crate::games::detect::expand_home;
+use crate::utils::error_handler::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// How many play sessions are kept per game, the total playtime counts all of them
+pub static PLAY_SESSION_LIMIT: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompatTool {
+    Wine,
+    Proton,
+}
+
+/*
+Runs Windows executables through Wine or Proton on Linux, e.g.
+"compat": { "tool": "proton", "binary": "~/.steam/steam/steamapps/common/Proton 9.0/proton", "prefix": "~/Games/bg3-prefix" }
+`binary` defaults to `wine` on the PATH, Proton needs both `binary` and `prefix`.
+*/
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatConfig {
+    pub tool: CompatTool,
+    pub binary: Option<String>,
+    pub prefix: Option<String>,
+}
+
+/*
+The "launch" section of a game's config.json, e.g.
+"launch": {
+  "executable": "bin/bg3.exe",
+  "args": ["--skip-launcher"],
+  "env": { "DXVK_ASYNC": "1" },
+  "workingDir": "bin"
+}
+The same shape is saved per game to override the community defaults.
+*/
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchConfig {
+    // Relative to the game folder, or absolute
+    pub executable: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    // Relative to the game folder, defaults to the executable's folder
+    pub working_dir: Option<String>,
+    pub compat: Option<CompatConfig>,
+}
+
+// A fully resolved launch, shown to the user before the game starts
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub working_dir: PathBuf,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaySession {
+    pub started_at: u64,
+    pub duration_secs: u64,
+    // None when the game was killed by a signal
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Playtime {
+    pub total_secs: u64,
+    pub launches: u64,
+    // Newest first, at most PLAY_SESSION_LIMIT
+    pub sessions: Vec<PlaySession>,
+}
+
+impl Playtime {
+    pub fn record(&mut self, session: PlaySession) {
+        self.total_secs += session.duration_secs;
+        self.launches += 1;
+        self.sessions.insert(0, session);
+        self.sessions.truncate(PLAY_SESSION_LIMIT);
+    }
+}
+
+impl LaunchConfig {
+    // Fields set in `overrides` win, env vars are merged
+    pub fn merge(mut self, overrides: LaunchConfig) -> LaunchConfig {
+        if overrides.executable.is_some() {
+            self.executable = overrides.executable;
+        }
+        if !overrides.args.is_empty() {
+            self.args = overrides.args;
+        }
+        self.env.extend(overrides.env);
+        if overrides.working_dir.is_some() {
+            self.working_dir = overrides.working_dir;
+        }
+        if overrides.compat.is_some() {
+            self.compat = overrides.compat;
+        }
+        self
+    }
+
+    pub fn resolve(
+        &self,
+        game_id: &str,
+        game_dir: &Path,
+        home: &Path,
+    ) -> Result<LaunchCommand, AppError> {
+        let executable = self.executable.as_ref().ok_or_else(|| {
+            AppError::LaunchError(format!("No executable is configured for '{}'", game_id))
+        })?;
+        let executable = game_dir.join(expand_home(executable, home));
+        if !executable.is_file() {
+            return Err(AppError::LaunchError(format!(
+                "Executable {:?} does not exist",
+                executable
+            )));
+        }
+        let working_dir = match &self.working_dir {
+            Some(dir) => game_dir.join(expand_home(dir, home)),
+            None => executable.parent().unwrap_or(game_dir).to_path_buf(),
+        };
+
+        let mut env = self.env.clone();
+        let is_windows_exe = executable
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("exe"))
+            .unwrap_or(false);
+        let (program, mut args) = match &self.compat {
+            Some(compat) if cfg!(target_os = "linux") && is_windows_exe => {
+                let prefix = compat.prefix.as_ref().map(|p| expand_home(p, home));
+                match compat.tool {
+                    CompatTool::Wine => {
+                        if let Some(prefix) = prefix {
+                            env.insert("WINEPREFIX".into(), prefix.to_string_lossy().to_string());
+                        }
+                        let wine = compat.binary.as_deref().unwrap_or("wine");
+                        (
+                            expand_home(wine, home),
+                            vec![executable.to_string_lossy().to_string()],
+                        )
+                    }
+                    CompatTool::Proton => {
+                        let (binary, prefix) = match (&compat.binary, prefix) {
+                            (Some(binary), Some(prefix)) => (expand_home(binary, home), prefix),
+                            _ => {
+                                return Err(AppError::LaunchError(
+                                    "Proton needs both a binary and a prefix".to_string(),
+                                ))
+                            }
+                        };
+                        env.insert(
+                            "STEAM_COMPAT_DATA_PATH".into(),
+                            prefix.to_string_lossy().to_string(),
+                        );
+                        env.entry("STEAM_COMPAT_CLIENT_INSTALL_PATH".into())
+                            .or_insert_with(|| {
+                                home.join(".steam/steam").to_string_lossy().to_string()
+                            });
+                        (
+                            binary,
+                            vec!["run".to_string(), executable.to_string_lossy().to_string()],
+                        )
+                    }
+                }
+            }
+            _ => (executable, Vec::new()),
+        };
+        args.extend(self.args.iter().cloned());
+
+        Ok(LaunchCommand {
+            program,
+            args,
+            env,
+            working_dir,
+        })
+    }
+}
+
+impl LaunchCommand {
+    // Output is piped so it can be streamed back to the frontend
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(&self.env)
+            .current_dir(&self.working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        command
+    }
+}
@@ -0,0 +1,11 @@
+pub mod backup;
+pub mod detect;
+pub mod launch;
+pub mod snapshot;
+pub mod steam;
+
+pub use backup::*;
+pub use detect::*;
+pub use launch::*;
+pub use snapshot::*;
+pub use steam::*;
@@ -0,0 +1,141 @@
+use crate::mods::ledger::unix_now;
+use crate::utils::error_handler::AppError;
+use crate::utils::files::walk_files;
+use crate::utils::hashing::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub static SNAPSHOTS_DIR: &str = "snapshots";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub hash: String,
+    pub size: u64,
+}
+
+// Baseline of a game folder, keyed by path relative to the folder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameSnapshot {
+    pub game_dir: PathBuf,
+    pub created_at: u64,
+    pub files: BTreeMap<PathBuf, SnapshotFile>,
+}
+
+// What the frontend gets back instead of the full file list
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    pub game_dir: PathBuf,
+    pub created_at: u64,
+    pub file_count: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub unchanged: usize,
+}
+
+impl GameSnapshot {
+    pub fn path(app_data: &Path, game_id: &str) -> PathBuf {
+        app_data
+            .join(SNAPSHOTS_DIR)
+            .join(format!("{}.json", game_id))
+    }
+
+    // Hashes every file under the game folder
+    pub fn capture(game_dir: &Path) -> Result<Self, AppError> {
+        let mut files = BTreeMap::new();
+        for file in walk_files(game_dir) {
+            let relative = match file.strip_prefix(game_dir) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue,
+            };
+            files.insert(
+                relative,
+                SnapshotFile {
+                    hash: hash_file(&file)?,
+                    size: file.metadata()?.len(),
+                },
+            );
+        }
+        Ok(Self {
+            game_dir: game_dir.to_path_buf(),
+            created_at: unix_now(),
+            files,
+        })
+    }
+
+    pub fn info(&self) -> SnapshotInfo {
+        SnapshotInfo {
+            game_dir: self.game_dir.clone(),
+            created_at: self.created_at,
+            file_count: self.files.len(),
+        }
+    }
+
+    pub fn load(app_data: &Path, game_id: &str) -> Result<Option<Self>, AppError> {
+        let path = Self::path(app_data, game_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| AppError::ParsingError(e.to_string()))
+    }
+
+    pub fn save(&self, app_data: &Path, game_id: &str) -> Result<(), AppError> {
+        let path = Self::path(app_data, game_id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content =
+            serde_json::to_string(self).map_err(|e| AppError::ParsingError(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    // Compares the game folder as it is now against this baseline
+    pub fn verify(&self) -> Result<VerifyReport, AppError> {
+        let mut report = VerifyReport::default();
+        let mut seen = 0;
+
+        for file in walk_files(&self.game_dir) {
+            let relative = match file.strip_prefix(&self.game_dir) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue,
+            };
+            let baseline = match self.files.get(&relative) {
+                Some(baseline) => baseline,
+                None => {
+                    report.added.push(relative);
+                    continue;
+                }
+            };
+            seen += 1;
+
+            // A size change is enough, only hash when sizes match
+            if file.metadata()?.len() != baseline.size || hash_file(&file)? != baseline.hash {
+                report.modified.push(relative);
+            } else {
+                report.unchanged += 1;
+            }
+        }
+
+        if seen < self.files.len() {
+            report.removed = self
+                .files
+                .keys()
+                .filter(|relative| !self.game_dir.join(relative).is_file())
+                .cloned()
+                .collect();
+        }
+        Ok(report)
+    }
+}
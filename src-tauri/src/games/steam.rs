@@ -0,0 +1,357 @@
+use std::path::{Path, PathBuf};
+
+/*
+Minimal reader for Valve's KeyValues text format (libraryfolders.vdf,
+appmanifest_*.acf). Quoted keys map to either a quoted string or a { block }.
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum VdfValue {
+    String(String),
+    Object(Vec<(String, VdfValue)>),
+}
+
+impl VdfValue {
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Object(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(s) => Some(s),
+            VdfValue::Object(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, VdfValue)] {
+        match self {
+            VdfValue::Object(entries) => entries,
+            VdfValue::String(_) => &[],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Text(String),
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '"' => {
+                let mut text = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(other) => text.push(other),
+                            None => break,
+                        },
+                        other => text.push(other),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                // Comment until end of line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            other => {
+                // Unquoted token
+                let mut text = other.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Text(text));
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_entries(tokens: &mut std::vec::IntoIter<Token>) -> Vec<(String, VdfValue)> {
+    let mut entries = Vec::new();
+    while let Some(token) = tokens.next() {
+        let key = match token {
+            Token::Text(key) => key,
+            Token::Close => break,
+            Token::Open => continue,
+        };
+        match tokens.next() {
+            Some(Token::Text(value)) => entries.push((key, VdfValue::String(value))),
+            Some(Token::Open) => entries.push((key, VdfValue::Object(parse_entries(tokens)))),
+            Some(Token::Close) | None => break,
+        }
+    }
+    entries
+}
+
+// Parses a whole vdf document into a root object
+pub fn parse_vdf(input: &str) -> VdfValue {
+    let mut tokens = tokenize(input).into_iter();
+    VdfValue::Object(parse_entries(&mut tokens))
+}
+
+// Usual Steam install locations, relative to the user's home folder where applicable
+pub fn steam_roots(home: &Path) -> Vec<PathBuf> {
+    let mut roots = vec![
+        home.join(".steam/steam"),
+        home.join(".local/share/Steam"),
+        // Flatpak and Snap installs
+        home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+        home.join("snap/steam/common/.local/share/Steam"),
+        home.join("Library/Application Support/Steam"),
+    ];
+    if cfg!(windows) {
+        roots.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+        roots.push(PathBuf::from(r"C:\Program Files\Steam"));
+    }
+    roots
+}
+
+// A Steam library folder, and the app ids Steam says are installed in it
+#[derive(Clone, Debug)]
+pub struct SteamLibrary {
+    pub path: PathBuf,
+    pub apps: Vec<String>,
+}
+
+impl SteamLibrary {
+    pub fn steamapps(&self) -> PathBuf {
+        self.path.join("steamapps")
+    }
+
+    // Whether libraryfolders.vdf lists the app as installed here
+    pub fn has_app(&self, app_id: &str) -> bool {
+        self.apps.iter().any(|id| id == app_id)
+    }
+
+    // Install folder name from the app's manifest, if Steam has one for it here
+    pub fn app_install_dir(&self, app_id: &str) -> Option<PathBuf> {
+        let manifest = self.steamapps().join(format!("appmanifest_{}.acf", app_id));
+        let content = std::fs::read_to_string(manifest).ok()?;
+        let install_dir = parse_vdf(&content)
+            .get("AppState")?
+            .get("installdir")?
+            .as_str()?
+            .to_string();
+        Some(self.steamapps().join("common").join(install_dir))
+    }
+}
+
+// Install folder of an app, from the library that lists it first and the other libraries' manifests after
+pub fn find_app_dir(libraries: &[SteamLibrary], app_id: &str) -> Option<PathBuf> {
+    let (listed, others): (Vec<&SteamLibrary>, Vec<&SteamLibrary>) = libraries
+        .iter()
+        .partition(|library| library.has_app(app_id));
+    listed
+        .into_iter()
+        .chain(others)
+        .find_map(|library| library.app_install_dir(app_id))
+}
+
+// Every library listed in libraryfolders.vdf under the known Steam roots, without duplicates
+pub fn steam_libraries(home: &Path) -> Vec<SteamLibrary> {
+    let mut libraries: Vec<SteamLibrary> = Vec::new();
+    for root in steam_roots(home) {
+        for vdf in [
+            root.join("steamapps/libraryfolders.vdf"),
+            root.join("config/libraryfolders.vdf"),
+        ] {
+            let content = match std::fs::read_to_string(&vdf) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let document = parse_vdf(&content);
+            let folders = match document.get("libraryfolders") {
+                Some(folders) => folders,
+                None => continue,
+            };
+
+            for (key, folder) in folders.entries() {
+                // Old format had plain `"1" "/path"` entries next to other settings
+                let path = match folder {
+                    VdfValue::String(path) if key.chars().all(|c| c.is_ascii_digit()) => {
+                        path.clone()
+                    }
+                    VdfValue::String(_) => continue,
+                    VdfValue::Object(_) => match folder.get("path").and_then(|p| p.as_str()) {
+                        Some(path) => path.to_string(),
+                        None => continue,
+                    },
+                };
+                let apps = folder
+                    .get("apps")
+                    .map(|apps| apps.entries().iter().map(|(id, _)| id.clone()).collect())
+                    .unwrap_or_default();
+
+                let path = PathBuf::from(path);
+                let canonical = path.canonicalize().unwrap_or(path.clone());
+                if libraries
+                    .iter()
+                    .any(|l| l.path.canonicalize().unwrap_or(l.path.clone()) == canonical)
+                {
+                    continue;
+                }
+                libraries.push(SteamLibrary { path, apps });
+            }
+        }
+
+        // The root itself is always a library, even without a vdf
+        if root.join("steamapps").is_dir()
+            && !libraries
+                .iter()
+                .any(|l| l.path.canonicalize().ok() == root.canonicalize().ok())
+        {
+            libraries.push(SteamLibrary {
+                path: root,
+                apps: Vec::new(),
+            });
+        }
+    }
+    libraries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::temp_dir;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn manifest(install_dir: &str) -> String {
+        format!(
+            r#""AppState"
+{{
+    "appid"      "1086940"
+    "installdir" "{}"
+}}"#,
+            install_dir
+        )
+    }
+
+    // Paths are escaped the way Steam writes them, e.g. "C:\\Games"
+    fn vdf_path(path: &Path) -> String {
+        path.to_string_lossy().replace('\\', "\\\\")
+    }
+
+    #[test]
+    fn parses_nested_blocks_escapes_and_comments() {
+        let document = parse_vdf(
+            r#"
+            // written by Steam
+            "libraryfolders"
+            {
+                "0"
+                {
+                    "path"  "C:\\Program Files (x86)\\Steam" // the default library
+                    "label" "say \"hi\""
+                    "apps"
+                    {
+                        "1086940"   "123456"
+                    }
+                }
+                contentstatsid unquoted
+            }
+            "#,
+        );
+        let folders = document.get("LibraryFolders").unwrap();
+        let folder = folders.get("0").unwrap();
+        assert_eq!(
+            folder.get("path").and_then(|p| p.as_str()),
+            Some(r"C:\Program Files (x86)\Steam")
+        );
+        assert_eq!(
+            folder.get("label").and_then(|l| l.as_str()),
+            Some(r#"say "hi""#)
+        );
+        assert_eq!(
+            folder.get("apps").unwrap().entries(),
+            &[(
+                "1086940".to_string(),
+                VdfValue::String("123456".to_string())
+            )]
+        );
+        assert_eq!(
+            folders.get("contentstatsid").and_then(|c| c.as_str()),
+            Some("unquoted")
+        );
+    }
+
+    #[test]
+    fn finds_the_app_in_the_library_that_lists_it() {
+        let home = temp_dir("steam-libraries");
+        let root = home.join(".steam/steam");
+        let games = home.join("Games/SteamLibrary");
+        write(
+            &root.join("steamapps/libraryfolders.vdf"),
+            &format!(
+                r#""libraryfolders"
+{{
+    "0" {{ "path" "{}" }}
+    "1"
+    {{
+        "path" "{}"
+        "apps" {{ "1086940" "0" }}
+    }}
+}}"#,
+                vdf_path(&root),
+                vdf_path(&games)
+            ),
+        );
+        // A stale manifest left in the default library after the game was moved
+        write(
+            &root.join("steamapps/appmanifest_1086940.acf"),
+            &manifest("Old Copy"),
+        );
+        write(
+            &games.join("steamapps/appmanifest_1086940.acf"),
+            &manifest("Baldurs Gate 3"),
+        );
+
+        let libraries = steam_libraries(&home);
+        assert_eq!(libraries.len(), 2);
+        assert!(libraries[1].has_app("1086940"));
+        assert_eq!(
+            find_app_dir(&libraries, "1086940"),
+            Some(games.join("steamapps/common/Baldurs Gate 3"))
+        );
+
+        // Without its manifest the listed library is skipped for the others
+        std::fs::remove_file(games.join("steamapps/appmanifest_1086940.acf")).unwrap();
+        assert_eq!(libraries[1].app_install_dir("1086940"), None);
+        assert_eq!(
+            find_app_dir(&libraries, "1086940"),
+            Some(root.join("steamapps/common/Old Copy"))
+        );
+        assert_eq!(find_app_dir(&libraries, "400"), None);
+
+        let _ = std::fs::remove_dir_all(home);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::context::parser;
+use crate::games::{expand_home, BackupInfo, BackupRules, BackupStore, PruneReport};
+use crate::invocable::settings::{
+    game_config_path, resolve_appdata_path, resolve_repo_path, DEFAULT_GAME_ID,
+};
+use crate::utils::error_handler::AppError;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tokio::task;
+
+// Backups made before installs, when the game's config.json doesn't set "keep"
+pub static DEFAULT_BACKUP_KEEP: usize = 10;
+
+fn load_backup_rules(app: &AppHandle, game_id: &str) -> Result<BackupRules, AppError> {
+    let repo_path = resolve_repo_path(app)?;
+    Ok(parser::load_json(game_config_path(&repo_path, game_id))
+        .ok()
+        .and_then(|config| config.get("backup").cloned())
+        .and_then(|backup| serde_json::from_value(backup).ok())
+        .unwrap_or_default())
+}
+
+fn backup_dirs(
+    app: &AppHandle,
+    rules: &BackupRules,
+) -> Result<BTreeMap<String, PathBuf>, AppError> {
+    let home = app.path().home_dir()?;
+    Ok(rules
+        .paths
+        .iter()
+        .map(|(name, path)| (name.clone(), expand_home(path, &home)))
+        .collect())
+}
+
+/*
+Backs up a game's save and config folders ahead of an install script,
+then prunes old backups. Does nothing for games without a "backup" section.
+*/
+pub fn backup_before_install(
+    app: &AppHandle,
+    game_id: &str,
+    mod_id: &str,
+) -> Result<Option<BackupInfo>, AppError> {
+    let rules = load_backup_rules(app, game_id)?;
+    if rules.paths.is_empty() {
+        return Ok(None);
+    }
+    let store = BackupStore::new(&resolve_appdata_path(app)?, game_id);
+    let backup = store.create(
+        &backup_dirs(app, &rules)?,
+        Some(format!("Before installing {}", mod_id)),
+    )?;
+    store.prune(rules.keep.unwrap_or(DEFAULT_BACKUP_KEEP))?;
+    Ok(Some(backup.info()))
+}
+
+#[tauri::command]
+pub async fn create_backup(
+    app: AppHandle,
+    game_id: Option<String>,
+    reason: Option<String>,
+) -> Result<BackupInfo, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let rules = load_backup_rules(&app, &game_id)?;
+    if rules.paths.is_empty() {
+        return Err(AppError::BackupError(format!(
+            "No save or config folders are declared for '{}'",
+            game_id
+        )));
+    }
+    let dirs = backup_dirs(&app, &rules)?;
+    let store = BackupStore::new(&resolve_appdata_path(&app)?, &game_id);
+    task::spawn_blocking(move || store.create(&dirs, reason).map(|b| b.info()))
+        .await
+        .map_err(|e| AppError::BackupError(e.to_string()))?
+}
+
+// Newest first
+#[tauri::command]
+pub async fn list_backups(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Vec<BackupInfo>, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let store = BackupStore::new(&resolve_appdata_path(&app)?, &game_id);
+    Ok(store.list()?.iter().map(|b| b.info()).collect())
+}
+
+// Current contents are backed up first, so a restore can itself be undone
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    backup_id: String,
+    game_id: Option<String>,
+    dirs: Option<Vec<String>>,
+) -> Result<BackupInfo, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let store = BackupStore::new(&resolve_appdata_path(&app)?, &game_id);
+    let current = store
+        .load(&backup_id)?
+        .dirs
+        .into_iter()
+        .map(|d| (d.name, d.path))
+        .collect::<BTreeMap<_, _>>();
+
+    task::spawn_blocking(move || {
+        store.create(&current, Some(format!("Before restoring {}", backup_id)))?;
+        store.restore(&backup_id, dirs.as_deref()).map(|b| b.info())
+    })
+    .await
+    .map_err(|e| AppError::BackupError(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn prune_backups(
+    app: AppHandle,
+    keep: usize,
+    game_id: Option<String>,
+) -> Result<PruneReport, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let store = BackupStore::new(&resolve_appdata_path(&app)?, &game_id);
+    task::spawn_blocking(move || store.prune(keep))
+        .await
+        .map_err(|e| AppError::BackupError(e.to_string()))?
+}
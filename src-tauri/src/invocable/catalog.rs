@@ -0,0 +1,43 @@
+use crate::context::manifest::ModManifest;
+use crate::invocable::settings::{resolve_repo_path, DEFAULT_GAME_ID};
+use crate::mods::{load_catalog, parse_request, resolve};
+use crate::utils::error_handler::AppError;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub async fn list_mods(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Vec<ModManifest>, AppError> {
+    let repo_path = resolve_repo_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    load_catalog(&repo_path, &game_id)
+}
+
+// Manifests for `id` or `id@<req>` requests plus their dependencies, dependencies first
+pub fn resolve_mod_ids(
+    app: &AppHandle,
+    game_id: &str,
+    mod_ids: &[String],
+) -> Result<Vec<ModManifest>, AppError> {
+    let repo_path = resolve_repo_path(app)?;
+    let catalog = load_catalog(&repo_path, game_id)?;
+
+    let requested = mod_ids
+        .iter()
+        .map(|id| parse_request(id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AppError::ResolveError)?;
+    resolve(&catalog, &requested).map_err(|e| AppError::ResolveError(e.to_string()))
+}
+
+// Returns the mods to install, dependencies first
+#[tauri::command]
+pub async fn resolve_mods(
+    app: AppHandle,
+    mod_ids: Vec<String>,
+    game_id: Option<String>,
+) -> Result<Vec<ModManifest>, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    resolve_mod_ids(&app, &game_id, &mod_ids)
+}
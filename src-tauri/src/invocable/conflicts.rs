@@ -0,0 +1,123 @@
+use crate::invocable::deploy::{deploy_game, load_deploy_config};
+use crate::invocable::settings::{resolve_appdata_path, DEFAULT_GAME_ID};
+use crate::mods::{
+    apply_conflicts, find_conflicts, mod_staging_dir, ConflictRules, Deployment, FileConflict,
+    Ledger,
+};
+use crate::utils::error_handler::AppError;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+fn current_conflicts(app_data: &Path, game_id: &str) -> Result<Vec<FileConflict>, AppError> {
+    let ledger = Ledger::load(app_data)?;
+    let rules = ConflictRules::load(app_data, game_id)?;
+    Ok(find_conflicts(&ledger, &rules, game_id))
+}
+
+// Saves the changed rules, then redeploys so every file matches its winner
+fn update_rules<F>(
+    app: &AppHandle,
+    game_id: Option<String>,
+    f: F,
+) -> Result<Vec<FileConflict>, AppError>
+where
+    F: FnOnce(&mut ConflictRules, &Ledger, &str) -> Result<(), AppError>,
+{
+    let app_data = resolve_appdata_path(app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let ledger = Ledger::load(&app_data)?;
+    let mut rules = ConflictRules::load(&app_data, &game_id)?;
+    f(&mut rules, &ledger, &game_id)?;
+    rules.save(&app_data, &game_id)?;
+
+    apply_conflicts(&app_data, &game_id)?;
+    if Deployment::load(&app_data, &game_id)?.is_some() {
+        deploy_game(app, &game_id)?;
+    }
+    current_conflicts(&app_data, &game_id)
+}
+
+// Whether a mod has a staged file that deploys to the given game path
+fn stages_file(
+    app: &AppHandle,
+    game_id: &str,
+    mod_id: &str,
+    path: &Path,
+) -> Result<bool, AppError> {
+    let app_data = resolve_appdata_path(app)?;
+    let game_dir = match load_deploy_config(app, game_id)?.game_dir {
+        Some(game_dir) => game_dir,
+        None => return Ok(false),
+    };
+    Ok(match path.strip_prefix(game_dir) {
+        Ok(relative) => mod_staging_dir(&app_data, game_id, mod_id)
+            .join(relative)
+            .is_file(),
+        Err(_) => false,
+    })
+}
+
+fn require_installed(ledger: &Ledger, game_id: &str, mod_id: &str) -> Result<(), AppError> {
+    match ledger.find(game_id, mod_id) {
+        Some(_) => Ok(()),
+        None => Err(AppError::InstallError(format!(
+            "Mod '{}' is not installed for '{}'",
+            mod_id, game_id
+        ))),
+    }
+}
+
+#[tauri::command]
+pub async fn list_conflicts(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Vec<FileConflict>, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    current_conflicts(&app_data, &game_id)
+}
+
+// Picks which mod provides a single file, None goes back to mod priority
+#[tauri::command]
+pub async fn set_file_winner(
+    app: AppHandle,
+    path: PathBuf,
+    mod_id: Option<String>,
+    game_id: Option<String>,
+) -> Result<Vec<FileConflict>, AppError> {
+    update_rules(&app, game_id, |rules, ledger, game_id| {
+        match mod_id {
+            Some(mod_id) => {
+                let record = ledger.find(game_id, &mod_id);
+                let installed = record.and_then(|r| r.file(&path)).is_some();
+                if !installed && !stages_file(&app, game_id, &mod_id, &path)? {
+                    return Err(AppError::InstallError(format!(
+                        "Mod '{}' does not provide {:?}",
+                        mod_id, path
+                    )));
+                }
+                rules.files.insert(path, mod_id);
+            }
+            None => {
+                rules.files.remove(&path);
+            }
+        }
+        Ok(())
+    })
+}
+
+// Mods later in the list win conflicts over earlier ones
+#[tauri::command]
+pub async fn set_mod_priority(
+    app: AppHandle,
+    mod_ids: Vec<String>,
+    game_id: Option<String>,
+) -> Result<Vec<FileConflict>, AppError> {
+    update_rules(&app, game_id, |rules, ledger, game_id| {
+        for mod_id in &mod_ids {
+            require_installed(ledger, game_id, mod_id)?;
+        }
+        rules.priority = mod_ids;
+        Ok(())
+    })
+}
@@ -0,0 +1,126 @@
+use crate::context::parser;
+use crate::invocable::loadorder::load_synced_order;
+use crate::invocable::settings::{
+    game_config_path, resolve_appdata_path, resolve_repo_path, DEFAULT_GAME_ID, SETTINGS_SOURCE,
+    STORE_PATH,
+};
+use crate::mods::{self, staged_mods, ConflictRules, DeployMethod, Deployment};
+use crate::utils::error_handler::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+// Where and how a game's staged mods get deployed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployConfig {
+    pub game_dir: Option<PathBuf>,
+    // Falls back to the game's config.json "deployMethod", then hardlinks
+    pub method: Option<DeployMethod>,
+}
+
+pub fn deploy_key(source: &str, game_id: &str) -> String {
+    format!("deploy/{}/{}", source, game_id)
+}
+
+pub fn load_deploy_config(app: &AppHandle, game_id: &str) -> Result<DeployConfig, AppError> {
+    let store = app.store(STORE_PATH)?;
+    Ok(store
+        .get(deploy_key(SETTINGS_SOURCE, game_id))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+fn default_method(app: &AppHandle, game_id: &str) -> DeployMethod {
+    resolve_repo_path(app)
+        .ok()
+        .and_then(|repo| parser::load_json(game_config_path(&repo, game_id)).ok())
+        .and_then(|config| config.get("deployMethod").cloned())
+        .and_then(|method| serde_json::from_value(method).ok())
+        .unwrap_or_default()
+}
+
+// Enabled mods in load order, then anything staged that isn't in the order yet.
+// Conflict rules still pick the winner when two of them stage the same file.
+fn deploy_order(app: &AppHandle, game_id: &str) -> Result<Vec<String>, AppError> {
+    let app_data = resolve_appdata_path(app)?;
+    let staged = staged_mods(&app_data, game_id);
+    let load_order = load_synced_order(app, game_id)?;
+
+    let mut order: Vec<String> = load_order
+        .entries
+        .iter()
+        .filter(|e| e.enabled && staged.contains(&e.mod_id))
+        .map(|e| e.mod_id.clone())
+        .collect();
+    for mod_id in staged {
+        if load_order.position(&mod_id).is_none() {
+            order.push(mod_id);
+        }
+    }
+    Ok(order)
+}
+
+// Redeploys every staged mod for a game, used by the command and after installs
+pub fn deploy_game(app: &AppHandle, game_id: &str) -> Result<Deployment, AppError> {
+    let config = load_deploy_config(app, game_id)?;
+    let game_dir = config.game_dir.ok_or_else(|| {
+        AppError::InstallError(format!("No game folder is set for '{}'", game_id))
+    })?;
+    let method = config
+        .method
+        .unwrap_or_else(|| default_method(app, game_id));
+
+    let app_data = resolve_appdata_path(app)?;
+    let order = deploy_order(app, game_id)?;
+    let rules = ConflictRules::load(&app_data, game_id)?;
+    mods::deploy(&app_data, game_id, &game_dir, &order, &rules, method)
+}
+
+#[tauri::command]
+pub async fn get_deploy_config(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<DeployConfig, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    load_deploy_config(&app, &game_id)
+}
+
+#[tauri::command]
+pub async fn set_deploy_config(
+    app: AppHandle,
+    config: DeployConfig,
+    game_id: Option<String>,
+) -> Result<DeployConfig, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let value = serde_json::to_value(&config).map_err(|e| AppError::ParsingError(e.to_string()))?;
+    store.set(deploy_key(SETTINGS_SOURCE, &game_id), value);
+    store.save()?;
+    Ok(config)
+}
+
+#[tauri::command]
+pub async fn get_deployment(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Option<Deployment>, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    Deployment::load(&app_data, &game_id)
+}
+
+#[tauri::command]
+pub async fn deploy_mods(app: AppHandle, game_id: Option<String>) -> Result<Deployment, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    deploy_game(&app, &game_id)
+}
+
+// Restores the game folder to vanilla, staged files are kept for the next deploy
+#[tauri::command]
+pub async fn purge_mods(app: AppHandle, game_id: Option<String>) -> Result<Vec<PathBuf>, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    mods::purge(&app_data, &game_id)
+}
@@ -0,0 +1,50 @@
+use crate::context::parser;
+use crate::games::{detect_game, DetectedGame, DetectionRules};
+use crate::invocable::settings::{game_config_path, resolve_repo_path, DEFAULT_GAME_ID};
+use crate::utils::error_handler::AppError;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+// Detection rules from a game's config.json, games without a "detect" section have none
+fn load_detection_rules(repo_path: &Path, game_id: &str) -> Option<DetectionRules> {
+    let config = parser::load_json(game_config_path(repo_path, game_id)).ok()?;
+    serde_json::from_value(config.get("detect")?.clone()).ok()
+}
+
+#[tauri::command]
+pub async fn detect_game_dir(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Vec<DetectedGame>, AppError> {
+    let repo_path = resolve_repo_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let home = app.path().home_dir()?;
+    Ok(load_detection_rules(&repo_path, &game_id)
+        .map(|rules| detect_game(&game_id, &rules, &home))
+        .unwrap_or_default())
+}
+
+// Candidates for every game in the community catalog
+#[tauri::command]
+pub async fn detect_games(app: AppHandle) -> Result<Vec<DetectedGame>, AppError> {
+    let repo_path = resolve_repo_path(&app)?;
+    let games_path = repo_path.join("games");
+    let home = app.path().home_dir()?;
+    if !games_path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut detected = Vec::new();
+    for entry in std::fs::read_dir(&games_path)? {
+        let entry = entry?;
+        // Stray files like .DS_Store aren't games
+        if !entry.path().is_dir() {
+            continue;
+        }
+        let game_id = entry.file_name().to_string_lossy().to_string();
+        if let Some(rules) = load_detection_rules(&repo_path, &game_id) {
+            detected.extend(detect_game(&game_id, &rules, &home));
+        }
+    }
+    Ok(detected)
+}
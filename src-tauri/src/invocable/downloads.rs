@@ -0,0 +1,228 @@
+use crate::context::manifest::ModManifest;
+use crate::downloads::{
+    normalize_sha256, DownloadBundle, DownloadCache, DownloadManager, DownloadProgress,
+    DownloadRequest, ProgressSink, SkippedDownload,
+};
+use crate::invocable::catalog::resolve_mod_ids;
+use crate::invocable::runner::{dry_run_plan, ScriptOptions};
+use crate::invocable::settings::{
+    resolve_appdata_path, DEFAULT_GAME_ID, SETTINGS_SOURCE, STORE_PATH,
+};
+use crate::utils::error_handler::AppError;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{ipc::Channel, AppHandle};
+use tauri_plugin_store::StoreExt;
+
+pub fn offline_key(source: &str) -> String {
+    format!("offline/{}", source)
+}
+
+// Offline mode makes `download` use only the cache and disables wget/http_client
+pub fn offline_mode(app: &AppHandle) -> bool {
+    app.store(STORE_PATH)
+        .ok()
+        .and_then(|store| store.get(offline_key(SETTINGS_SOURCE)))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+fn progress_sink(on_event: Channel<DownloadProgress>) -> ProgressSink {
+    Arc::new(move |progress: &DownloadProgress| {
+        let _ = on_event.send(progress.clone());
+    })
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadResult {
+    pub url: String,
+    pub path: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+// Downloads files in parallel, one result per request in request order
+#[tauri::command]
+pub async fn download_files(
+    app: AppHandle,
+    requests: Vec<DownloadRequest>,
+    on_event: Channel<DownloadProgress>,
+) -> Result<Vec<DownloadResult>, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let manager = DownloadManager::new(DownloadCache::new(&app_data))
+        .with_offline(offline_mode(&app))
+        .with_progress(progress_sink(on_event));
+
+    tokio::task::spawn_blocking(move || {
+        let results = manager.fetch_all(&requests);
+        requests
+            .into_iter()
+            .zip(results)
+            .map(|(request, result)| match result {
+                Ok(path) => DownloadResult {
+                    url: request.url,
+                    path: Some(path),
+                    error: None,
+                },
+                Err(e) => DownloadResult {
+                    url: request.url,
+                    path: None,
+                    error: Some(e.to_string()),
+                },
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| AppError::DownloadError(e.to_string()))
+}
+
+// Empties the download cache, returning the bytes freed
+#[tauri::command]
+pub async fn clear_download_cache(app: AppHandle) -> Result<u64, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    Ok(DownloadCache::new(&app_data).clear()?)
+}
+
+#[tauri::command]
+pub async fn get_offline_mode(app: AppHandle) -> Result<bool, AppError> {
+    Ok(offline_mode(&app))
+}
+
+#[tauri::command]
+pub async fn set_offline_mode(app: AppHandle, enabled: bool) -> Result<(), AppError> {
+    let store = app.store(STORE_PATH)?;
+    store.set(offline_key(SETTINGS_SOURCE), Value::Bool(enabled));
+    store.save()?;
+    Ok(())
+}
+
+/*
+Dry runs each mudfile to find what it downloads, then fetches every file
+into a bundle folder that import_download_bundle can load into another
+machine's cache. Files already in the local cache aren't downloaded again.
+*/
+pub async fn prefetch_bundle(
+    app: &AppHandle,
+    game_id: &str,
+    manifests: &[ModManifest],
+    bundle_dir: &Path,
+    progress: Option<ProgressSink>,
+) -> Result<DownloadBundle, AppError> {
+    let app_data = resolve_appdata_path(app)?;
+    let mut bundle = DownloadBundle::load(bundle_dir)?;
+    let mut requests = Vec::new();
+    for manifest in manifests {
+        let path = match manifest.path {
+            Some(ref path) => path,
+            None => continue,
+        };
+        let script = std::fs::read_to_string(path)?;
+        let options = ScriptOptions {
+            game_id: Some(game_id.to_string()),
+            mod_id: Some(manifest.id.clone()),
+            script_path: Some(path.clone()),
+            ..Default::default()
+        };
+        let plan = dry_run_plan(app.clone(), script, options)
+            .await
+            .map_err(|e| {
+                AppError::DownloadError(format!("Dry run of '{}' failed: {}", manifest.id, e))
+            })?;
+
+        for download in plan.downloads {
+            match download.sha256 {
+                Some(sha256) => requests.push((manifest.id.clone(), download.url, sha256)),
+                None => bundle.skipped.push(SkippedDownload {
+                    url: download.url,
+                    mod_id: manifest.id.clone(),
+                    reason: format!(
+                        "`{}` has no checksum, use `download --sha256`",
+                        download.command
+                    ),
+                }),
+            }
+        }
+    }
+
+    let mut manager =
+        DownloadManager::new(DownloadCache::new(&app_data)).with_offline(offline_mode(app));
+    if let Some(progress) = progress {
+        manager = manager.with_progress(progress);
+    }
+    let fetch: Vec<DownloadRequest> = requests
+        .iter()
+        .map(|(_, url, sha256)| DownloadRequest {
+            url: url.clone(),
+            sha256: sha256.clone(),
+            destination: None,
+        })
+        .collect();
+    let results = tokio::task::spawn_blocking(move || manager.fetch_all(&fetch))
+        .await
+        .map_err(|e| AppError::DownloadError(e.to_string()))?;
+
+    // Files that did download are kept in the bundle even when others failed
+    let bundled = DownloadBundle::cache(bundle_dir);
+    let total = requests.len();
+    let mut failures = Vec::new();
+    for ((mod_id, url, sha256), result) in requests.into_iter().zip(results) {
+        let sha256 = normalize_sha256(&sha256).unwrap_or(sha256);
+        let imported = result.and_then(|cached| {
+            bundled.import(&sha256, &cached)?;
+            Ok(cached.metadata().map(|m| m.len()).unwrap_or(0))
+        });
+        match imported {
+            Ok(size) => bundle.add(&url, &sha256, size, &mod_id),
+            Err(e) => failures.push(format!("{} ({}): {}", url, mod_id, e)),
+        }
+    }
+    bundle.save(bundle_dir)?;
+
+    if !failures.is_empty() {
+        return Err(AppError::DownloadError(format!(
+            "{} of {} downloads failed, the rest were saved to the bundle:\n{}",
+            failures.len(),
+            total,
+            failures.join("\n")
+        )));
+    }
+    Ok(bundle)
+}
+
+// Prefetches the downloads of the given mods and their dependencies
+#[tauri::command]
+pub async fn prefetch_downloads(
+    app: AppHandle,
+    mod_ids: Vec<String>,
+    bundle_dir: PathBuf,
+    game_id: Option<String>,
+    on_event: Channel<DownloadProgress>,
+) -> Result<DownloadBundle, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let manifests = resolve_mod_ids(&app, &game_id, &mod_ids)?;
+    prefetch_bundle(
+        &app,
+        &game_id,
+        &manifests,
+        &bundle_dir,
+        Some(progress_sink(on_event)),
+    )
+    .await
+}
+
+// Loads a bundle made by prefetch_downloads into the download cache
+#[tauri::command]
+pub async fn import_download_bundle(
+    app: AppHandle,
+    bundle_dir: PathBuf,
+) -> Result<usize, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let bundle = DownloadBundle::load(&bundle_dir)?;
+    tokio::task::spawn_blocking(move || {
+        bundle.import_into(&bundle_dir, &DownloadCache::new(&app_data))
+    })
+    .await
+    .map_err(|e| AppError::DownloadError(e.to_string()))?
+}
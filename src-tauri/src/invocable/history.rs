@@ -0,0 +1,138 @@
+use crate::invocable::profiles::{load_profiles, save_profiles, GameProfiles};
+use crate::invocable::settings::{
+    load_game_schema, schema_defaults, DEFAULT_GAME_ID, SETTINGS_SOURCE, STORE_PATH,
+};
+use crate::utils::error_handler::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+pub static HISTORY_LIMIT: usize = 20;
+
+// One saved state of a profile, newest entries are kept at the front
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SettingsSnapshot {
+    pub timestamp: u64,
+    pub profile: String,
+    pub values: Map<String, Value>,
+}
+
+pub fn history_key(source: &str, game_id: &str) -> String {
+    format!("history/{}/{}", source, game_id)
+}
+
+pub fn load_history(store: &Arc<Store<Wry>>, game_id: &str) -> Vec<SettingsSnapshot> {
+    store
+        .get(history_key(SETTINGS_SOURCE, game_id))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+// Pushes the given values onto the history, dropping anything past HISTORY_LIMIT
+pub fn record_snapshot(
+    store: &Arc<Store<Wry>>,
+    game_id: &str,
+    profile: &str,
+    values: &Map<String, Value>,
+) -> Result<(), AppError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut history = load_history(store, game_id);
+    history.insert(
+        0,
+        SettingsSnapshot {
+            timestamp,
+            profile: profile.to_string(),
+            values: values.clone(),
+        },
+    );
+    history.truncate(HISTORY_LIMIT);
+
+    let value =
+        serde_json::to_value(&history).map_err(|e| AppError::ParsingError(e.to_string()))?;
+    store.set(history_key(SETTINGS_SOURCE, game_id), value);
+    Ok(())
+}
+
+// Writes new values to the active profile and records them in the history
+fn apply_values(
+    store: &Arc<Store<Wry>>,
+    game_id: &str,
+    mut game_profiles: GameProfiles,
+    values: Map<String, Value>,
+) -> Result<GameProfiles, AppError> {
+    record_snapshot(store, game_id, &game_profiles.active, &values)?;
+    game_profiles.set_active_values(values);
+    save_profiles(store, game_id, &game_profiles)?;
+    Ok(game_profiles)
+}
+
+#[tauri::command]
+pub async fn get_settings_history(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Vec<SettingsSnapshot>, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    Ok(load_history(&store, &game_id))
+}
+
+#[tauri::command]
+pub async fn reset_setting(
+    app: AppHandle,
+    key: String,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let schema = load_game_schema(app.clone(), &store, &game_id)?;
+    let defaults = schema_defaults(&schema);
+
+    let game_profiles = load_profiles(&store, &game_id);
+    let mut values = game_profiles.active_values();
+    match defaults.get(&key) {
+        Some(default) => values.insert(key, default.clone()),
+        None => values.remove(&key),
+    };
+    apply_values(&store, &game_id, game_profiles, values)
+}
+
+#[tauri::command]
+pub async fn reset_all_settings(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let schema = load_game_schema(app.clone(), &store, &game_id)?;
+
+    let game_profiles = load_profiles(&store, &game_id);
+    apply_values(&store, &game_id, game_profiles, schema_defaults(&schema))
+}
+
+#[tauri::command]
+pub async fn revert_settings(
+    app: AppHandle,
+    index: usize,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let snapshot = load_history(&store, &game_id)
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| AppError::ProfileError(format!("No settings snapshot at {}", index)))?;
+
+    // Snapshots go back into the profile they were taken from
+    let mut game_profiles = load_profiles(&store, &game_id);
+    if game_profiles.profiles.contains_key(&snapshot.profile) {
+        game_profiles.active = snapshot.profile;
+    }
+    apply_values(&store, &game_id, game_profiles, snapshot.values)
+}
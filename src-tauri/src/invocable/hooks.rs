@@ -0,0 +1,81 @@
+use crate::context::hooks::Hook;
+use crate::invocable::loadorder::{installed_manifests, load_synced_order};
+use crate::invocable::runner::{
+    expand_includes, run_hook, PayloadEvent, RunTracking, ScriptOptions,
+};
+use crate::utils::error_handler::AppError;
+use tauri::{ipc::Channel, AppHandle};
+
+// Runs one hook of an installed mod's mudfile, as found in the catalog
+pub async fn run_mod_hook(
+    app: &AppHandle,
+    game_id: &str,
+    mod_id: &str,
+    hook: Hook,
+    on_event: Option<Channel<PayloadEvent>>,
+) -> Result<Option<String>, AppError> {
+    let manifest = match installed_manifests(app, game_id)?
+        .into_iter()
+        .find(|m| m.id == mod_id)
+    {
+        Some(manifest) => manifest,
+        None => return Ok(None),
+    };
+    let script = match manifest.path {
+        Some(ref path) => std::fs::read_to_string(path)?,
+        None => return Ok(None),
+    };
+    let options = ScriptOptions {
+        game_id: Some(game_id.to_string()),
+        mod_id: Some(mod_id.to_string()),
+        mod_version: manifest.version.as_ref().map(|v| v.to_string()),
+        script_path: manifest.path.clone(),
+        ..Default::default()
+    };
+    let failed = |e: String| {
+        AppError::InstallError(format!(
+            "{} hook of '{}' failed: {}",
+            hook.function_name(),
+            mod_id,
+            e
+        ))
+    };
+    let script = expand_includes(app, &script, &options).map_err(failed)?;
+    run_hook(
+        app,
+        &script,
+        hook,
+        on_event,
+        &options,
+        &RunTracking::default(),
+    )
+    .await
+    .map_err(failed)
+}
+
+// Runs the on_launch hook of every enabled mod, in load order
+pub async fn run_launch_hooks(
+    app: &AppHandle,
+    game_id: &str,
+    on_event: Option<Channel<PayloadEvent>>,
+) -> Result<Vec<String>, AppError> {
+    let mut ran = Vec::new();
+    for entry in load_synced_order(app, game_id)?.entries {
+        if !entry.enabled {
+            continue;
+        }
+        if run_mod_hook(
+            app,
+            game_id,
+            &entry.mod_id,
+            Hook::OnLaunch,
+            on_event.clone(),
+        )
+        .await?
+        .is_some()
+        {
+            ran.push(entry.mod_id);
+        }
+    }
+    Ok(ran)
+}
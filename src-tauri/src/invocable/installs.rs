@@ -0,0 +1,66 @@
+use crate::context::hooks::Hook;
+use crate::invocable::deploy::deploy_game;
+use crate::invocable::hooks::run_mod_hook;
+use crate::invocable::settings::{resolve_appdata_path, DEFAULT_GAME_ID};
+use crate::mods::{self, mod_staging_dir, Deployment, InstallRecord, Ledger, RestoreReport};
+use crate::utils::error_handler::AppError;
+use tauri::AppHandle;
+
+#[tauri::command]
+pub async fn list_installed_mods(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Vec<InstallRecord>, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let ledger = Ledger::load(&app_data)?;
+    Ok(ledger.for_game(&game_id).into_iter().cloned().collect())
+}
+
+#[tauri::command]
+pub async fn uninstall_mod(
+    app: AppHandle,
+    mod_id: String,
+    game_id: Option<String>,
+    force: Option<bool>,
+) -> Result<RestoreReport, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let force = force.unwrap_or(false);
+
+    // The mod is only torn down when the uninstall will go ahead
+    let check = mods::check_uninstall(&app_data, &game_id, &mod_id)?;
+    if !check.modified.is_empty() && !force {
+        return Ok(check);
+    }
+    // A failing pre_uninstall hook stops the uninstall, unless forced
+    if let Err(e) = run_mod_hook(&app, &game_id, &mod_id, Hook::PreUninstall, None).await {
+        if !force {
+            return Err(e);
+        }
+        eprintln!("{}", e);
+    }
+    let report = mods::uninstall(&app_data, &game_id, &mod_id, force)?;
+
+    // Take the mod's staged files back out of the game folder
+    if report.applied && Deployment::load(&app_data, &game_id)?.is_some() {
+        let staged = mod_staging_dir(&app_data, &game_id, &mod_id);
+        if staged.exists() {
+            std::fs::remove_dir_all(staged)?;
+        }
+        deploy_game(&app, &game_id)?;
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn rollback_install(
+    app: AppHandle,
+    mod_id: String,
+    game_id: Option<String>,
+    force: Option<bool>,
+) -> Result<RestoreReport, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    mods::rollback(&app_data, &game_id, &mod_id, force.unwrap_or(false))
+}
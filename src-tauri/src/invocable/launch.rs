@@ -0,0 +1,198 @@
+use crate::context::parser;
+use crate::games::{LaunchCommand, LaunchConfig, PlaySession, Playtime};
+use crate::invocable::deploy::load_deploy_config;
+use crate::invocable::hooks::run_launch_hooks;
+use crate::invocable::runner::PayloadEvent;
+use crate::invocable::settings::{
+    game_config_path, resolve_repo_path, DEFAULT_GAME_ID, SETTINGS_SOURCE, STORE_PATH,
+};
+use crate::mods::ledger::unix_now;
+use crate::utils::error_handler::AppError;
+use std::io::{BufRead, BufReader, ErrorKind, Read};
+use std::time::Instant;
+use tauri::{ipc::Channel, AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+use tokio::task;
+
+pub fn launch_key(source: &str, game_id: &str) -> String {
+    format!("launch/{}/{}", source, game_id)
+}
+
+pub fn playtime_key(source: &str, game_id: &str) -> String {
+    format!("playtime/{}/{}", source, game_id)
+}
+
+// The user's saved launch settings, without the community defaults
+fn load_launch_overrides(app: &AppHandle, game_id: &str) -> Result<LaunchConfig, AppError> {
+    let store = app.store(STORE_PATH)?;
+    Ok(store
+        .get(launch_key(SETTINGS_SOURCE, game_id))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+// The game's config.json "launch" section with the user's saved settings on top
+pub fn load_launch_config(app: &AppHandle, game_id: &str) -> Result<LaunchConfig, AppError> {
+    let defaults: LaunchConfig = resolve_repo_path(app)
+        .ok()
+        .and_then(|repo| parser::load_json(game_config_path(&repo, game_id)).ok())
+        .and_then(|config| config.get("launch").cloned())
+        .and_then(|launch| serde_json::from_value(launch).ok())
+        .unwrap_or_default();
+    Ok(defaults.merge(load_launch_overrides(app, game_id)?))
+}
+
+fn resolve_launch(app: &AppHandle, game_id: &str) -> Result<LaunchCommand, AppError> {
+    let game_dir = load_deploy_config(app, game_id)?
+        .game_dir
+        .ok_or_else(|| AppError::LaunchError(format!("No game folder is set for '{}'", game_id)))?;
+    let home = app.path().home_dir()?;
+    load_launch_config(app, game_id)?.resolve(game_id, &game_dir, &home)
+}
+
+fn load_playtime(app: &AppHandle, game_id: &str) -> Result<Playtime, AppError> {
+    let store = app.store(STORE_PATH)?;
+    Ok(store
+        .get(playtime_key(SETTINGS_SOURCE, game_id))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+/*
+Sends each line of a child's output as an event until the pipe closes. Lines
+that aren't utf-8 are sent lossily, and the pipe keeps being drained after the
+channel is gone, a closed pipe would kill the game on its next write.
+*/
+fn stream_lines<R: Read>(reader: R, on_event: &Channel<PayloadEvent>, stderr: bool) {
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+    let mut listening = true;
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+        if !listening {
+            continue;
+        }
+        let line = String::from_utf8_lossy(&buffer)
+            .trim_end_matches(['\n', '\r'])
+            .to_string();
+        let event = match stderr {
+            true => PayloadEvent::Stderr { message: line },
+            false => PayloadEvent::Stdout { message: line },
+        };
+        listening = on_event.send(event).is_ok();
+    }
+}
+
+// Runs the game until it exits, streaming its output
+fn run_game(
+    launch: &LaunchCommand,
+    on_event: &Channel<PayloadEvent>,
+) -> Result<PlaySession, AppError> {
+    let started_at = unix_now();
+    let started = Instant::now();
+    let mut child = launch.command().spawn().map_err(|e| {
+        AppError::LaunchError(format!("Failed to start {:?}: {}", launch.program, e))
+    })?;
+    let _ = on_event.send(PayloadEvent::Started {
+        message: format!("Started {:?} (pid {})", launch.program, child.id()),
+    });
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let status = std::thread::scope(|scope| {
+        if let Some(stdout) = stdout {
+            scope.spawn(|| stream_lines(stdout, on_event, false));
+        }
+        if let Some(stderr) = stderr {
+            scope.spawn(|| stream_lines(stderr, on_event, true));
+        }
+        child.wait()
+    })?;
+
+    let session = PlaySession {
+        started_at,
+        duration_secs: started.elapsed().as_secs(),
+        exit_code: status.code(),
+    };
+    let _ = on_event.send(PayloadEvent::Finished {
+        message: match session.exit_code {
+            Some(code) => format!("Exited with code {}", code),
+            None => "Exited without an exit code".to_string(),
+        },
+    });
+    Ok(session)
+}
+
+#[tauri::command]
+pub async fn get_launch_config(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<LaunchConfig, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    load_launch_config(&app, &game_id)
+}
+
+// Saves overrides for the community launch settings
+#[tauri::command]
+pub async fn set_launch_config(
+    app: AppHandle,
+    config: LaunchConfig,
+    game_id: Option<String>,
+) -> Result<LaunchConfig, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let value = serde_json::to_value(&config).map_err(|e| AppError::ParsingError(e.to_string()))?;
+    store.set(launch_key(SETTINGS_SOURCE, &game_id), value);
+    store.save()?;
+    load_launch_config(&app, &game_id)
+}
+
+// What launch_game would run, without running it
+#[tauri::command]
+pub async fn preview_launch(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<LaunchCommand, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    resolve_launch(&app, &game_id)
+}
+
+/*
+Runs the enabled mods' on_launch hooks, then the game itself. Resolves once
+the game exits, with the session that was added to the playtime record.
+*/
+#[tauri::command]
+pub async fn launch_game(
+    app: AppHandle,
+    game_id: Option<String>,
+    on_event: Channel<PayloadEvent>,
+) -> Result<PlaySession, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let launch = resolve_launch(&app, &game_id)?;
+    run_launch_hooks(&app, &game_id, Some(on_event.clone())).await?;
+
+    let session = task::spawn_blocking(move || run_game(&launch, &on_event))
+        .await
+        .map_err(|e| AppError::LaunchError(e.to_string()))??;
+
+    let mut playtime = load_playtime(&app, &game_id)?;
+    playtime.record(session.clone());
+    let value =
+        serde_json::to_value(&playtime).map_err(|e| AppError::ParsingError(e.to_string()))?;
+    let store = app.store(STORE_PATH)?;
+    store.set(playtime_key(SETTINGS_SOURCE, &game_id), value);
+    store.save()?;
+    Ok(session)
+}
+
+#[tauri::command]
+pub async fn get_playtime(app: AppHandle, game_id: Option<String>) -> Result<Playtime, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    load_playtime(&app, &game_id)
+}
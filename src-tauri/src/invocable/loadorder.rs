@@ -0,0 +1,266 @@
+use crate::context::manifest::ModManifest;
+use crate::context::parser;
+use crate::invocable::profiles::load_profiles;
+use crate::invocable::settings::{
+    game_config_path, resolve_appdata_path, resolve_repo_path, DEFAULT_GAME_ID, SETTINGS_SOURCE,
+    STORE_PATH,
+};
+use crate::mods::{
+    load_catalog, write_load_order as write_order_file, writer_for, Ledger, LoadOrder, LoadRule,
+    WriterEntry,
+};
+use crate::utils::error_handler::AppError;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+// Orders are kept per profile, so switching profiles switches load order too
+pub fn load_order_key(source: &str, game_id: &str, profile: &str) -> String {
+    format!("loadorder/{}/{}/{}", source, game_id, profile)
+}
+
+fn active_load_order_key(store: &Arc<Store<Wry>>, game_id: &str) -> String {
+    let profile = load_profiles(store, game_id).active;
+    load_order_key(SETTINGS_SOURCE, game_id, &profile)
+}
+
+// Mod ids installed for a game, in install order
+fn installed_mod_ids(app: &AppHandle, game_id: &str) -> Result<Vec<String>, AppError> {
+    let app_data = resolve_appdata_path(app)?;
+    let ledger = Ledger::load(&app_data)?;
+    let mut records = ledger.for_game(game_id);
+    records.sort_by_key(|r| r.installed_at);
+    Ok(records.into_iter().map(|r| r.mod_id.clone()).collect())
+}
+
+// Catalog manifests for the installed mods, matching the installed version when possible
+pub fn installed_manifests(app: &AppHandle, game_id: &str) -> Result<Vec<ModManifest>, AppError> {
+    let app_data = resolve_appdata_path(app)?;
+    let ledger = Ledger::load(&app_data)?;
+    let catalog = load_catalog(&resolve_repo_path(app)?, game_id)?;
+
+    Ok(ledger
+        .for_game(game_id)
+        .into_iter()
+        .filter_map(|record| {
+            let versions: Vec<&ModManifest> =
+                catalog.iter().filter(|m| m.id == record.mod_id).collect();
+            versions
+                .iter()
+                .find(|m| m.version.as_ref().map(|v| v.to_string()) == record.version)
+                .or(versions.first())
+                .map(|m| (*m).clone())
+        })
+        .collect())
+}
+
+// Stored order for the active profile, synced with what's currently installed
+pub fn load_synced_order(app: &AppHandle, game_id: &str) -> Result<LoadOrder, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let mut load_order: LoadOrder = store
+        .get(active_load_order_key(&store, game_id))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    load_order.sync(&installed_mod_ids(app, game_id)?);
+    Ok(load_order)
+}
+
+pub fn save_load_order(app: &AppHandle, game_id: &str, load_order: &LoadOrder) -> Result<(), AppError> {
+    let store = app.store(STORE_PATH)?;
+    let value =
+        serde_json::to_value(load_order).map_err(|e| AppError::ParsingError(e.to_string()))?;
+    store.set(active_load_order_key(&store, game_id), value);
+    store.save()?;
+    Ok(())
+}
+
+fn update_load_order<F>(
+    app: &AppHandle,
+    game_id: Option<String>,
+    f: F,
+) -> Result<LoadOrder, AppError>
+where
+    F: FnOnce(&mut LoadOrder) -> Result<(), AppError>,
+{
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let mut load_order = load_synced_order(app, &game_id)?;
+    f(&mut load_order)?;
+    save_load_order(app, &game_id, &load_order)?;
+    Ok(load_order)
+}
+
+fn require_mod(load_order: &LoadOrder, mod_id: &str) -> Result<usize, AppError> {
+    load_order
+        .position(mod_id)
+        .ok_or_else(|| AppError::LoadOrderError(format!("Mod '{}' is not installed", mod_id)))
+}
+
+fn cycle_error(mod_ids: Vec<String>) -> AppError {
+    AppError::ResolveError(format!(
+        "load order rules form a cycle between {}",
+        mod_ids.join(", ")
+    ))
+}
+
+#[tauri::command]
+pub async fn get_load_order(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<LoadOrder, AppError> {
+    update_load_order(&app, game_id, |_| Ok(()))
+}
+
+// Reorders using manifest after/before hints and user rules, leaving locked mods in place
+#[tauri::command]
+pub async fn sort_load_order(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<LoadOrder, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let manifests = installed_manifests(&app, &game_id)?;
+    update_load_order(&app, Some(game_id), |load_order| {
+        load_order.sort(&manifests).map_err(cycle_error)
+    })
+}
+
+// Manual order from the UI, mods left out keep their relative order at the end
+#[tauri::command]
+pub async fn set_load_order(
+    app: AppHandle,
+    mod_ids: Vec<String>,
+    game_id: Option<String>,
+) -> Result<LoadOrder, AppError> {
+    update_load_order(&app, game_id, |load_order| {
+        let mut entries = Vec::new();
+        for mod_id in &mod_ids {
+            let index = require_mod(load_order, mod_id)?;
+            entries.push(load_order.entries.remove(index));
+        }
+        entries.append(&mut load_order.entries);
+        load_order.entries = entries;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn set_mod_enabled(
+    app: AppHandle,
+    mod_id: String,
+    enabled: bool,
+    game_id: Option<String>,
+) -> Result<LoadOrder, AppError> {
+    update_load_order(&app, game_id, |load_order| {
+        let index = require_mod(load_order, &mod_id)?;
+        load_order.entries[index].enabled = enabled;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn set_mod_locked(
+    app: AppHandle,
+    mod_id: String,
+    locked: bool,
+    game_id: Option<String>,
+) -> Result<LoadOrder, AppError> {
+    update_load_order(&app, game_id, |load_order| {
+        let index = require_mod(load_order, &mod_id)?;
+        load_order.entries[index].locked = locked;
+        Ok(())
+    })
+}
+
+// User rule that `before` loads ahead of `after`, checked by sorting straight away
+#[tauri::command]
+pub async fn add_load_rule(
+    app: AppHandle,
+    before: String,
+    after: String,
+    game_id: Option<String>,
+) -> Result<LoadOrder, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let manifests = installed_manifests(&app, &game_id)?;
+    update_load_order(&app, Some(game_id), |load_order| {
+        if before == after {
+            return Err(AppError::LoadOrderError(
+                "A mod cannot load before itself".to_string(),
+            ));
+        }
+        let rule = LoadRule { before, after };
+        if !load_order.rules.contains(&rule) {
+            load_order.rules.push(rule);
+        }
+        load_order.sort(&manifests).map_err(cycle_error)
+    })
+}
+
+#[tauri::command]
+pub async fn remove_load_rule(
+    app: AppHandle,
+    before: String,
+    after: String,
+    game_id: Option<String>,
+) -> Result<LoadOrder, AppError> {
+    update_load_order(&app, game_id, |load_order| {
+        load_order
+            .rules
+            .retain(|r| !(r.before == before && r.after == after));
+        Ok(())
+    })
+}
+
+/*
+Writes the order to the file the game reads, as declared in its config.json:
+"loadOrder": { "format": "plugins.txt", "path": "~/path/to/plugins.txt" }
+*/
+#[tauri::command]
+pub async fn write_load_order(
+    app: AppHandle,
+    game_id: Option<String>,
+    target: Option<PathBuf>,
+) -> Result<PathBuf, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let repo_path = resolve_repo_path(&app)?;
+    let config = parser::load_json(game_config_path(&repo_path, &game_id))
+        .map_err(|e| AppError::ParsingError(e.to_string()))?;
+    let declared = config.get("loadOrder").ok_or_else(|| {
+        AppError::LoadOrderError(format!("'{}' does not declare a load order file", game_id))
+    })?;
+
+    let format = declared
+        .get("format")
+        .and_then(|f| f.as_str())
+        .unwrap_or_default();
+    let writer = writer_for(format).ok_or_else(|| {
+        AppError::LoadOrderError(format!("Unknown load order format '{}'", format))
+    })?;
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let path = declared
+                .get("path")
+                .and_then(|p| p.as_str())
+                .ok_or_else(|| {
+                    AppError::LoadOrderError("Load order file has no path".to_string())
+                })?;
+            match path.strip_prefix("~/") {
+                Some(rest) => app.path().home_dir()?.join(rest),
+                None => PathBuf::from(path),
+            }
+        }
+    };
+
+    let load_order = load_synced_order(&app, &game_id)?;
+    let manifests = installed_manifests(&app, &game_id)?;
+    let entries: Vec<WriterEntry> = load_order
+        .entries
+        .iter()
+        .map(|entry| WriterEntry {
+            entry,
+            manifest: manifests.iter().find(|m| m.id == entry.mod_id),
+        })
+        .collect();
+    write_order_file(writer.as_ref(), &entries, &target)?;
+    Ok(target)
+}
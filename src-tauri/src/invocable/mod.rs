@@ -1,7 +1,39 @@
+pub mod backups;
+pub mod catalog;
+pub mod conflicts;
+pub mod deploy;
+pub mod detection;
+pub mod downloads;
 pub mod git;
+pub mod history;
+pub mod hooks;
+pub mod installs;
+pub mod launch;
+pub mod loadorder;
+pub mod packs;
+pub mod profiles;
 pub mod runner;
 pub mod settings;
+pub mod snapshots;
+pub mod transfer;
+pub mod updates;
 
+pub use backups::*;
+pub use catalog::*;
+pub use conflicts::*;
+pub use deploy::*;
+pub use detection::*;
+pub use downloads::*;
 pub use git::*;
+pub use history::*;
+pub use hooks::*;
+pub use installs::*;
+pub use launch::*;
+pub use loadorder::*;
+pub use packs::*;
+pub use profiles::*;
 pub use runner::*;
 pub use settings::*;
+pub use snapshots::*;
+pub use transfer::*;
+pub use updates::*;
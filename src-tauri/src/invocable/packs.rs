@@ -0,0 +1,259 @@
+use crate::downloads::{DownloadBundle, DownloadCache, DownloadProgress, ProgressSink};
+use crate::invocable::catalog::resolve_mod_ids;
+use crate::invocable::downloads::prefetch_bundle;
+use crate::invocable::git::find_last_commit;
+use crate::invocable::loadorder::{load_synced_order, save_load_order};
+use crate::invocable::profiles::{load_profiles, save_profiles};
+use crate::invocable::runner::{exec_script, expand_includes, PayloadEvent, ScriptOptions};
+use crate::invocable::settings::{
+    load_game_schema, resolve_appdata_path, resolve_repo_path, validate_settings, DEFAULT_GAME_ID,
+    STORE_PATH,
+};
+use crate::mods::{
+    pack_work_dir, unix_now, unpack, write_pack, ModPack, PackSettings, PackedMod,
+    PACK_DOWNLOADS_DIR, PACK_VERSION,
+};
+use crate::utils::error_handler::AppError;
+use git2::Repository;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{ipc::Channel, AppHandle};
+use tauri_plugin_store::StoreExt;
+use tokio::task;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackInstall {
+    pub name: String,
+    pub game_id: String,
+    pub installed: Vec<String>,
+    pub downloads: usize,
+    pub repo_commit: Option<String>,
+    // Differs from repo_commit when this machine's repo is on another commit
+    pub local_commit: Option<String>,
+}
+
+fn repo_commit(app: &AppHandle) -> Option<String> {
+    let repo = Repository::open(resolve_repo_path(app).ok()?).ok()?;
+    let commit = find_last_commit(&repo).ok()?;
+    Some(commit.id().to_string())
+}
+
+fn pack_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "modpack".to_string())
+}
+
+// Builds the pack folder, then zips it to `path`
+async fn build_pack(
+    app: &AppHandle,
+    work_dir: &Path,
+    path: &Path,
+    name: String,
+    game_id: String,
+    mod_ids: &[String],
+    progress: Option<ProgressSink>,
+) -> Result<ModPack, AppError> {
+    let manifests = resolve_mod_ids(app, &game_id, mod_ids)?;
+    let mut mods = Vec::new();
+    for manifest in &manifests {
+        let source = manifest.path.as_ref().ok_or_else(|| {
+            AppError::InstallError(format!("No mudfile found for '{}'", manifest.id))
+        })?;
+        // Included files aren't packed, so they're inlined into the packed mudfile
+        let options = ScriptOptions {
+            game_id: Some(game_id.clone()),
+            script_path: Some(source.clone()),
+            ..Default::default()
+        };
+        let expanded = expand_includes(app, &std::fs::read_to_string(source)?, &options)
+            .map_err(|e| AppError::InstallError(format!("'{}': {}", manifest.id, e)))?;
+        let mudfile = ModPack::mudfile_path(&manifest.id);
+        let target = work_dir.join(&mudfile);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, expanded.script)?;
+        mods.push(PackedMod {
+            id: manifest.id.clone(),
+            version: manifest.version.as_ref().map(|v| v.to_string()),
+            mudfile,
+        });
+    }
+
+    // Without a progress sink the pack is built without downloads
+    if let Some(progress) = progress {
+        prefetch_bundle(
+            app,
+            &game_id,
+            &manifests,
+            &work_dir.join(PACK_DOWNLOADS_DIR),
+            Some(progress),
+        )
+        .await?;
+    }
+
+    let store = app.store(STORE_PATH)?;
+    let game_profiles = load_profiles(&store, &game_id);
+    let pack = ModPack {
+        version: PACK_VERSION,
+        name,
+        created_at: unix_now(),
+        repo_commit: repo_commit(app),
+        mods,
+        settings: Some(PackSettings {
+            active: game_profiles.active,
+            profiles: game_profiles.profiles,
+        }),
+        load_order: Some(load_synced_order(app, &game_id)?),
+        game_id,
+    };
+    pack.save(work_dir)?;
+    let (work_dir, path) = (work_dir.to_path_buf(), path.to_path_buf());
+    task::spawn_blocking(move || write_pack(&work_dir, &path))
+        .await
+        .map_err(|e| AppError::InstallError(e.to_string()))??;
+    Ok(pack)
+}
+
+/*
+Exports the given mods (with their dependencies), the game's settings
+profiles, its load order and, unless disabled, every checksummed download
+the mudfiles use into a single zip.
+*/
+#[tauri::command]
+pub async fn export_mod_pack(
+    app: AppHandle,
+    path: PathBuf,
+    mod_ids: Vec<String>,
+    name: Option<String>,
+    game_id: Option<String>,
+    include_downloads: Option<bool>,
+    on_event: Channel<DownloadProgress>,
+) -> Result<ModPack, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let name = name.unwrap_or_else(|| pack_name(&path));
+    let work_dir = pack_work_dir(&resolve_appdata_path(&app)?, "export");
+
+    let progress: Option<ProgressSink> = match include_downloads.unwrap_or(true) {
+        true => Some(Arc::new(move |progress: &DownloadProgress| {
+            let _ = on_event.send(progress.clone());
+        })),
+        false => None,
+    };
+
+    let result = build_pack(&app, &work_dir, &path, name, game_id, &mod_ids, progress).await;
+    if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+        eprintln!("Failed to clean up {:?}: {}", work_dir, e);
+    }
+    result
+}
+
+async fn apply_pack(
+    app: &AppHandle,
+    work_dir: &Path,
+    game_id: Option<String>,
+    on_event: Option<Channel<PayloadEvent>>,
+) -> Result<PackInstall, AppError> {
+    let pack = ModPack::load(work_dir)?;
+    let game_id = game_id.unwrap_or(pack.game_id.clone());
+
+    // Downloads first, so installs can run offline
+    let bundle_dir = work_dir.join(PACK_DOWNLOADS_DIR);
+    let downloads = if bundle_dir.is_dir() {
+        let cache = DownloadCache::new(&resolve_appdata_path(app)?);
+        let bundle = DownloadBundle::load(&bundle_dir)?;
+        task::spawn_blocking(move || bundle.import_into(&bundle_dir, &cache))
+            .await
+            .map_err(|e| AppError::DownloadError(e.to_string()))??
+    } else {
+        0
+    };
+
+    // Packed profiles replace same-named local ones, like import_settings
+    let store = app.store(STORE_PATH)?;
+    if let Some(settings) = &pack.settings {
+        let schema = load_game_schema(app.clone(), &store, &game_id)?;
+        for (name, values) in &settings.profiles {
+            validate_settings(&schema, &Value::Object(values.clone()))
+                .map_err(|e| AppError::ParsingError(format!("Profile '{}': {}", name, e)))?;
+        }
+        let mut game_profiles = load_profiles(&store, &game_id);
+        for (name, values) in &settings.profiles {
+            game_profiles.profiles.insert(name.clone(), values.clone());
+        }
+        if game_profiles.profiles.contains_key(&settings.active) {
+            game_profiles.active = settings.active.clone();
+        }
+        save_profiles(&store, &game_id, &game_profiles)?;
+    }
+
+    let mut installed = Vec::new();
+    for packed in &pack.mods {
+        let script = pack.read_mudfile(work_dir, packed)?;
+        let options = ScriptOptions {
+            game_id: Some(game_id.clone()),
+            mod_id: Some(packed.id.clone()),
+            mod_version: packed.version.clone(),
+            transactional: true,
+            script_path: Some(work_dir.join(&packed.mudfile)),
+            ..Default::default()
+        };
+        exec_script(app.clone(), script, on_event.clone(), options)
+            .await
+            .map_err(|e| {
+                AppError::InstallError(format!("Failed to install '{}': {}", packed.id, e))
+            })?;
+        installed.push(packed.id.clone());
+    }
+
+    // Saved last, the load order syncs against what's installed
+    if let Some(load_order) = &pack.load_order {
+        save_load_order(app, &game_id, load_order)?;
+    }
+
+    Ok(PackInstall {
+        name: pack.name,
+        game_id,
+        installed,
+        downloads,
+        repo_commit: pack.repo_commit,
+        local_commit: repo_commit(app),
+    })
+}
+
+// Unpacks a mod pack and installs it, used by the command and the CLI
+pub async fn install_mod_pack(
+    app: &AppHandle,
+    path: &Path,
+    game_id: Option<String>,
+    on_event: Option<Channel<PayloadEvent>>,
+) -> Result<PackInstall, AppError> {
+    let work_dir = pack_work_dir(&resolve_appdata_path(app)?, "import");
+    let (archive, target) = (path.to_path_buf(), work_dir.clone());
+    let unpacked = match task::spawn_blocking(move || unpack(&archive, &target)).await {
+        Ok(unpacked) => unpacked,
+        Err(e) => Err(AppError::InstallError(e.to_string())),
+    };
+    let result = match unpacked {
+        Ok(_) => apply_pack(app, &work_dir, game_id, on_event).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+        eprintln!("Failed to clean up {:?}: {}", work_dir, e);
+    }
+    result
+}
+
+#[tauri::command]
+pub async fn import_mod_pack(
+    app: AppHandle,
+    path: PathBuf,
+    game_id: Option<String>,
+    on_event: Channel<PayloadEvent>,
+) -> Result<PackInstall, AppError> {
+    install_mod_pack(&app, &path, game_id, Some(on_event)).await
+}
@@ -0,0 +1,223 @@
+use crate::invocable::settings::{settings_key, DEFAULT_GAME_ID, SETTINGS_SOURCE, STORE_PATH};
+use crate::utils::error_handler::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Wry};
+use tauri_plugin_store::{JsonValue, Store, StoreExt};
+
+pub static DEFAULT_PROFILE: &str = "default";
+
+/*
+Everything saved for one game lives in a single store entry:
+{ "active": "default", "profiles": { "default": { <setting key>: <value>, ... } } }
+*/
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameProfiles {
+    pub active: String,
+    pub profiles: BTreeMap<String, Map<String, Value>>,
+}
+
+impl Default for GameProfiles {
+    fn default() -> Self {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(DEFAULT_PROFILE.to_string(), Map::new());
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            profiles,
+        }
+    }
+}
+
+impl GameProfiles {
+    // Accepts both the profile document and the older flat map of values
+    pub fn from_json(value: JsonValue) -> Self {
+        if value.get("profiles").is_some() {
+            if let Ok(parsed) = serde_json::from_value::<GameProfiles>(value.clone()) {
+                return parsed;
+            }
+        }
+
+        let mut game_profiles = GameProfiles::default();
+        if let Value::Object(values) = value {
+            game_profiles
+                .profiles
+                .insert(DEFAULT_PROFILE.to_string(), values);
+        }
+        game_profiles
+    }
+
+    pub fn active_values(&self) -> Map<String, Value> {
+        self.profiles.get(&self.active).cloned().unwrap_or_default()
+    }
+
+    pub fn set_active_values(&mut self, values: Map<String, Value>) {
+        self.profiles.insert(self.active.clone(), values);
+    }
+
+    fn require(&self, name: &str) -> Result<(), AppError> {
+        if self.profiles.contains_key(name) {
+            Ok(())
+        } else {
+            Err(AppError::ProfileError(format!(
+                "Profile '{}' does not exist",
+                name
+            )))
+        }
+    }
+
+    fn require_free(&self, name: &str) -> Result<(), AppError> {
+        if name.trim().is_empty() {
+            return Err(AppError::ProfileError(
+                "Profile name cannot be empty".to_string(),
+            ));
+        }
+        if self.profiles.contains_key(name) {
+            return Err(AppError::ProfileError(format!(
+                "Profile '{}' already exists",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub fn load_profiles(store: &Arc<Store<Wry>>, game_id: &str) -> GameProfiles {
+    store
+        .get(settings_key(SETTINGS_SOURCE, game_id))
+        .map(GameProfiles::from_json)
+        .unwrap_or_default()
+}
+
+pub fn save_profiles(
+    store: &Arc<Store<Wry>>,
+    game_id: &str,
+    game_profiles: &GameProfiles,
+) -> Result<(), AppError> {
+    let value =
+        serde_json::to_value(game_profiles).map_err(|e| AppError::ParsingError(e.to_string()))?;
+    store.set(settings_key(SETTINGS_SOURCE, game_id), value);
+    store.save()?;
+    Ok(())
+}
+
+// Values of the active profile, used when merging forms and seeding script variables
+pub fn active_settings(app: &AppHandle, game_id: &str) -> Result<Map<String, Value>, AppError> {
+    let store = app.store(STORE_PATH)?;
+    Ok(load_profiles(&store, game_id).active_values())
+}
+
+fn update_profiles<F>(
+    app: &AppHandle,
+    game_id: Option<String>,
+    f: F,
+) -> Result<GameProfiles, AppError>
+where
+    F: FnOnce(&mut GameProfiles) -> Result<(), AppError>,
+{
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let mut game_profiles = load_profiles(&store, &game_id);
+    f(&mut game_profiles)?;
+    save_profiles(&store, &game_id, &game_profiles)?;
+    Ok(game_profiles)
+}
+
+#[tauri::command]
+pub async fn list_profiles(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    Ok(load_profiles(&store, &game_id))
+}
+
+#[tauri::command]
+pub async fn create_profile(
+    app: AppHandle,
+    name: String,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    update_profiles(&app, game_id, |game_profiles| {
+        game_profiles.require_free(&name)?;
+        game_profiles.profiles.insert(name, Map::new());
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn clone_profile(
+    app: AppHandle,
+    source: String,
+    name: String,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    update_profiles(&app, game_id, |game_profiles| {
+        game_profiles.require(&source)?;
+        game_profiles.require_free(&name)?;
+        let values = game_profiles.profiles[&source].clone();
+        game_profiles.profiles.insert(name, values);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn rename_profile(
+    app: AppHandle,
+    name: String,
+    new_name: String,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    update_profiles(&app, game_id, |game_profiles| {
+        game_profiles.require(&name)?;
+        game_profiles.require_free(&new_name)?;
+        if let Some(values) = game_profiles.profiles.remove(&name) {
+            game_profiles.profiles.insert(new_name.clone(), values);
+        }
+        if game_profiles.active == name {
+            game_profiles.active = new_name;
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn delete_profile(
+    app: AppHandle,
+    name: String,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    update_profiles(&app, game_id, |game_profiles| {
+        game_profiles.require(&name)?;
+        if game_profiles.profiles.len() == 1 {
+            return Err(AppError::ProfileError(
+                "Cannot delete the only remaining profile".to_string(),
+            ));
+        }
+        game_profiles.profiles.remove(&name);
+        if game_profiles.active == name {
+            game_profiles.active = game_profiles
+                .profiles
+                .keys()
+                .next()
+                .cloned()
+                .unwrap_or_default();
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn activate_profile(
+    app: AppHandle,
+    name: String,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    update_profiles(&app, game_id, |game_profiles| {
+        game_profiles.require(&name)?;
+        game_profiles.active = name;
+        Ok(())
+    })
+}
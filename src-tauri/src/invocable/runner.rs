@@ -2,10 +2,32 @@ use duckscript::runner;
 use duckscript::types::runtime::Context;
 use duckscriptsdk;
 
-use crate::output::OutputCapture;
+use crate::commands::{
+    block_network_commands, load_mud_commands, record_commands, scope_commands, stage_commands,
+    track_commands,
+};
+use crate::context::environment::{ScriptEnv, MUD_APPDATA, MUD_GAME_DIR, MUD_GAME_ID, MUD_REPO};
+use crate::context::hooks::{hook_script, Hook};
+use crate::context::includes::{ExpandedScript, IncludeResolver};
+use crate::context::manifest::parse_manifest;
+use crate::context::setup_context_with_settings;
+use crate::downloads::{DownloadCache, DownloadManager, DownloadProgress};
+use crate::invocable::backups::backup_before_install;
+use crate::invocable::deploy::{deploy_game, load_deploy_config};
+use crate::invocable::downloads::offline_mode;
+use crate::invocable::profiles::active_settings;
+use crate::invocable::settings::{resolve_appdata_path, resolve_repo_path};
+use crate::mods::{
+    apply_conflicts, commit_transaction, discard_transaction, mod_staging_dir, mods_dir,
+    record_install, rollback_session, InstallSession, Ledger, SharedSession, SharedTransaction,
+    Transaction,
+};
+use crate::output::{OutputCapture, ScriptPlan, SharedPlan};
+use crate::utils::error_handler::AppError;
 use crate::utils::handle_script_error;
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -13,27 +35,299 @@ use tokio::{self, task};
 
 use tauri::{ipc::Channel, AppHandle, Listener};
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct ScriptResponse {
     stdout: String,
     stderr: String,
     variables: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<ScriptPlan>,
 }
 
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase", tag = "event", content = "data")]
 pub enum PayloadEvent {
     Stdout { message: String },
-    _Stderr { message: String },
-    _Started { message: String },
-    _Finished { message: String },
+    Download(DownloadProgress),
+    Stderr { message: String },
+    Started { message: String },
+    Finished { message: String },
 }
 
+// Per-run options, passed from the frontend as an optional `options` object
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptOptions {
+    pub game_id: Option<String>,
+    // Runs with both a game and mod id are recorded in the install ledger
+    pub mod_id: Option<String>,
+    pub mod_version: Option<String>,
+    // Stages filesystem writes and only applies them if the script succeeds
+    #[serde(default)]
+    pub transactional: bool,
+    // Records side effects into a plan instead of performing them
+    #[serde(default)]
+    pub dry_run: bool,
+    // Set while running one of a mudfile's lifecycle hooks
+    #[serde(skip)]
+    pub hook: Option<Hook>,
+    // Working directory for the run, defaults to the game folder, then the mudfile's folder
+    pub cwd: Option<PathBuf>,
+    // Env vars set for the run on top of the app's own
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    // Mudfile the script was read from, if any
+    pub script_path: Option<PathBuf>,
+}
+
+/*
+Resolves the run's working directory and env overlay. MUD_APPDATA, MUD_REPO,
+MUD_GAME_ID and MUD_GAME_DIR are set whenever they're known and can't be
+overridden by the overlay.
+*/
+fn script_env(handle: &AppHandle, options: &ScriptOptions) -> Result<ScriptEnv, AppError> {
+    let game_dir = options
+        .game_id
+        .as_ref()
+        .and_then(|game_id| load_deploy_config(handle, game_id).ok())
+        .and_then(|config| config.game_dir)
+        .filter(|dir| dir.is_dir());
+    let script_dir = options
+        .script_path
+        .as_ref()
+        .and_then(|path| path.parent())
+        .filter(|dir| dir.is_dir())
+        .map(|dir| dir.to_path_buf());
+
+    let mut vars = options.env.clone();
+    let path_var = |path: &PathBuf| path.to_string_lossy().to_string();
+    let app_data = resolve_appdata_path(handle)?;
+    vars.insert(MUD_APPDATA.to_string(), path_var(&app_data));
+    if let Ok(repo) = resolve_repo_path(handle) {
+        vars.insert(MUD_REPO.to_string(), path_var(&repo));
+    }
+    if let Some(ref game_id) = options.game_id {
+        vars.insert(MUD_GAME_ID.to_string(), game_id.clone());
+    }
+    if let Some(ref dir) = game_dir {
+        vars.insert(MUD_GAME_DIR.to_string(), path_var(dir));
+    }
+
+    Ok(ScriptEnv {
+        cwd: options.cwd.clone().or(game_dir).or(script_dir),
+        vars,
+        ..Default::default()
+    })
+}
+
+// Inlines `!include_files`, looking in the game's mods folder and the repo after the including file's folder
+pub fn expand_includes(
+    handle: &AppHandle,
+    script_content: &str,
+    options: &ScriptOptions,
+) -> Result<ExpandedScript, String> {
+    let mut search_paths = Vec::new();
+    if let Ok(repo) = resolve_repo_path(handle) {
+        if let Some(ref game_id) = options.game_id {
+            search_paths.push(mods_dir(&repo, game_id));
+        }
+        search_paths.push(repo);
+    }
+    IncludeResolver::new(search_paths)
+        .expand(script_content, options.script_path.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/*
+The install session and transaction a script shares with its hooks, so what
+pre_install and post_install write is tracked and staged with the install.
+*/
+#[derive(Clone, Default)]
+pub struct RunTracking {
+    pub session: Option<SharedSession>,
+    pub transaction: Option<SharedTransaction>,
+}
+
+fn run_tracking(
+    app_data: &Path,
+    options: &ScriptOptions,
+    installing: bool,
+) -> Result<RunTracking, AppError> {
+    let session = match (&options.game_id, &options.mod_id) {
+        (Some(game_id), Some(mod_id)) if installing => {
+            let ledger = Ledger::load(app_data)?;
+            let previous = ledger.find(game_id, mod_id);
+            Some(InstallSession::new(app_data, game_id, mod_id, previous).shared())
+        }
+        _ => None,
+    };
+    let transaction = match options.transactional && !options.dry_run {
+        true => Some(Transaction::new(app_data).shared()),
+        false => None,
+    };
+    Ok(RunTracking {
+        session,
+        transaction,
+    })
+}
+
+/*
+Runs a script, between its pre_install and post_install hooks when it's an
+install. The install is only recorded once post_install is done, a failing
+hook rolls it back like a failing script.
+*/
 pub async fn exec_script(
     handle: AppHandle,
     script_content: String,
     on_event: Option<Channel<PayloadEvent>>,
+    mut options: ScriptOptions,
+) -> Result<String, String> {
+    // Mod id and version default to the mudfile's manifest header
+    if let Some(manifest) = parse_manifest(&script_content)? {
+        options.mod_id = options.mod_id.or(Some(manifest.id));
+        options.mod_version = options
+            .mod_version
+            .or(manifest.version.map(|v| v.to_string()));
+    }
+
+    // Included files can define hooks too
+    let script = expand_includes(&handle, &script_content, &options)?;
+
+    // Dry runs only plan the install itself
+    let installing = options.hook.is_none()
+        && !options.dry_run
+        && options.game_id.is_some()
+        && options.mod_id.is_some();
+    if let (true, Some(game_id), Some(mod_id)) = (installing, &options.game_id, &options.mod_id) {
+        // Saves and configs are backed up before the install or its hooks can touch them
+        let (app, game, id) = (handle.clone(), game_id.clone(), mod_id.clone());
+        match task::spawn_blocking(move || backup_before_install(&app, &game, &id)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => eprintln!("Failed to back up saves before installing: {}", e),
+            Err(e) => eprintln!("Failed to back up saves before installing: {}", e),
+        }
+    }
+
+    let app_data = resolve_appdata_path(&handle).map_err(|e| e.to_string())?;
+    let tracking = run_tracking(&app_data, &options, installing).map_err(|e| e.to_string())?;
+    let result = async {
+        if installing {
+            run_hook(
+                &handle,
+                &script,
+                Hook::PreInstall,
+                on_event.clone(),
+                &options,
+                &tracking,
+            )
+            .await?;
+        }
+        let result = exec_single(
+            handle.clone(),
+            script.clone(),
+            on_event.clone(),
+            options.clone(),
+            tracking.clone(),
+        )
+        .await?;
+        if installing {
+            run_hook(
+                &handle,
+                &script,
+                Hook::PostInstall,
+                on_event.clone(),
+                &options,
+                &tracking,
+            )
+            .await?;
+        }
+        Ok(result)
+    }
+    .await;
+
+    // Committing, recording and deploying all touch the disk, so they run off the async runtime
+    task::spawn_blocking(move || {
+        finish_run(
+            &handle,
+            &app_data,
+            result,
+            tracking.transaction.as_ref(),
+            tracking.session.as_ref(),
+            &options,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/*
+Calls a hook if the mudfile defines it, returning None when it doesn't.
+`script` must already have its includes expanded.
+*/
+pub async fn run_hook(
+    handle: &AppHandle,
+    script: &ExpandedScript,
+    hook: Hook,
+    on_event: Option<Channel<PayloadEvent>>,
+    options: &ScriptOptions,
+    tracking: &RunTracking,
+) -> Result<Option<String>, String> {
+    let body = match hook_script(&script.script, hook) {
+        Some(body) => ExpandedScript {
+            script: body,
+            lines: script.lines.clone(),
+        },
+        None => return Ok(None),
+    };
+    let options = ScriptOptions {
+        hook: Some(hook),
+        ..options.clone()
+    };
+    exec_single(handle.clone(), body, on_event, options, tracking.clone())
+        .await
+        .map(Some)
+}
+
+async fn exec_single(
+    handle: AppHandle,
+    script: ExpandedScript,
+    on_event: Option<Channel<PayloadEvent>>,
+    options: ScriptOptions,
+    tracking: RunTracking,
 ) -> Result<String, String> {
+    // Settings from the game's active profile
+    let settings = match options.game_id {
+        Some(ref game_id) => active_settings(&handle, game_id).map_err(|e| e.to_string())?,
+        None => Default::default(),
+    };
+
+    let app_data = resolve_appdata_path(&handle).map_err(|e| e.to_string())?;
+
+    // Mods can write into their staging folder instead of the game folder, see deploy.rs
+    let script_staging_dir = match (&options.game_id, &options.mod_id) {
+        (Some(game_id), Some(mod_id)) => Some(mod_staging_dir(&app_data, game_id, mod_id)),
+        _ => None,
+    };
+    let script_hook = options.hook;
+    let run_env = script_env(&handle, &options)
+        .map_err(|e| e.to_string())?
+        .shared();
+
+    // `download` reports progress through the same channel as stdout
+    let offline = offline_mode(&handle);
+    let mut downloads = DownloadManager::new(DownloadCache::new(&app_data)).with_offline(offline);
+    if let Some(ref event_handler) = on_event {
+        let event_handler = event_handler.clone();
+        downloads = downloads.with_progress(Arc::new(move |progress: &DownloadProgress| {
+            let _ = event_handler.send(PayloadEvent::Download(progress.clone()));
+        }));
+    }
+
+    let plan: Option<SharedPlan> = if options.dry_run {
+        Some(Default::default())
+    } else {
+        None
+    };
 
     // Atomic booleans for quiting tasks prematurely
     let halt_flag = Arc::new(AtomicBool::new(false));
@@ -56,13 +350,43 @@ pub async fn exec_script(
         let env = output_capture.as_env();
         let mut context = Context::new();
         duckscriptsdk::load(&mut context.commands).unwrap();
+        load_mud_commands(&mut context.commands, downloads, run_env.clone())
+            .map_err(|e| e.to_string())?;
+        if offline {
+            block_network_commands(&mut context.commands).map_err(|e| e.to_string())?;
+        }
+        setup_context_with_settings(&mut context, &settings);
+        if let Ok(run_env) = run_env.lock() {
+            run_env.setup_context(&mut context);
+        }
+        if let Some(dir) = script_staging_dir {
+            context.variables.insert(
+                "staging_dir".to_string(),
+                dir.to_string_lossy().to_string(),
+            );
+        }
+        if let Some(hook) = script_hook {
+            context
+                .variables
+                .insert("mud_hook".to_string(), hook.function_name().to_string());
+        }
+        match (plan.clone(), tracking.transaction, tracking.session) {
+            (Some(plan), _, _) => record_commands(&mut context.commands, plan),
+            (None, Some(transaction), _) => stage_commands(&mut context.commands, transaction),
+            (None, None, Some(session)) => track_commands(&mut context.commands, session),
+            (None, None, None) => Ok(()),
+        }
+        .map_err(|e| e.to_string())?;
+        scope_commands(&mut context.commands, run_env).map_err(|e| e.to_string())?;
 
-        match runner::run_script(&script_content, context, Some(env)) {
+        match runner::run_script(&script.script, context, Some(env)) {
             Ok(ctx) => {
                 let response = ScriptResponse {
                     stdout: output_capture.get_stdout(),
                     stderr: output_capture.get_stderr(),
                     variables: ctx.variables,
+                    plan: plan
+                        .and_then(|p| p.lock().ok().map(|p| p.clone().map_lines(&script.lines))),
                 };
                 let json = serde_json::to_string(&response).unwrap_or_else(|_| {
                     "{\"message\": \"Failed to serialize response\"".to_string()
@@ -72,7 +396,7 @@ pub async fn exec_script(
             Err(err) => {
                 let stdout = output_capture.get_stdout();
                 let stderr = output_capture.get_stderr();
-                let json = handle_script_error(err, stderr, stdout);
+                let json = handle_script_error(err, stderr, stdout, &script.lines);
                 Err(json)
             }
         }
@@ -113,13 +437,77 @@ pub async fn exec_script(
     }
 }
 
+/*
+Applies staged writes only when the whole script succeeded, then records what
+a successful run changed or undoes partial changes from a failed one.
+*/
+fn finish_run(
+    handle: &AppHandle,
+    app_data: &Path,
+    result: Result<String, String>,
+    transaction: Option<&SharedTransaction>,
+    session: Option<&SharedSession>,
+    options: &ScriptOptions,
+) -> Result<String, String> {
+    let result = match (result, transaction) {
+        (Ok(json), Some(transaction)) => commit_transaction(transaction, session)
+            .map(|_| json)
+            .map_err(|e| e.to_string()),
+        (Err(err), Some(transaction)) => {
+            if let Err(e) = discard_transaction(transaction) {
+                eprintln!("Failed to discard staged changes: {}", e);
+            }
+            Err(err)
+        }
+        (result, None) => result,
+    };
+
+    let session = match session {
+        Some(session) => session,
+        None => return result,
+    };
+    if result.is_err() {
+        if let Err(e) = rollback_session(session) {
+            eprintln!("Failed to roll back partial installation: {}", e);
+        }
+        return result;
+    }
+
+    if let Err(e) = record_install(app_data, session, options.mod_version.clone()) {
+        eprintln!("Failed to record installation: {}", e);
+    }
+    if let (Some(game_id), Some(mod_id)) = (&options.game_id, &options.mod_id) {
+        // The new install may have overwritten files another mod is set to win
+        if let Err(e) = apply_conflicts(app_data, game_id) {
+            eprintln!("Failed to apply conflict winners: {}", e);
+        }
+        // Staged files only reach the game folder once deployed
+        let configured = load_deploy_config(handle, game_id)
+            .map(|c| c.game_dir.is_some())
+            .unwrap_or(false);
+        if configured && mod_staging_dir(app_data, game_id, mod_id).exists() {
+            if let Err(e) = deploy_game(handle, game_id) {
+                eprintln!("Failed to deploy staged files: {}", e);
+            }
+        }
+    }
+    result
+}
+
 #[tauri::command]
 pub async fn run_script(
     handle: AppHandle,
     script_content: String,
     on_event: Channel<PayloadEvent>,
+    options: Option<ScriptOptions>,
 ) -> Result<String, String> {
-    exec_script(handle, script_content, Some(on_event)).await
+    exec_script(
+        handle,
+        script_content,
+        Some(on_event),
+        options.unwrap_or_default(),
+    )
+    .await
 }
 
 #[tauri::command]
@@ -127,15 +515,36 @@ pub async fn run_scriptfile(
     file_path: String,
     handle: AppHandle,
     on_event: Channel<PayloadEvent>,
+    options: Option<ScriptOptions>,
 ) -> Result<String, String> {
     let script_content = std::fs::read_to_string(&file_path)
         .map_err(|e| format!("FAILED TO READ FILE: {:?}", e))?;
-    exec_script(handle, script_content, Some(on_event)).await
+    let mut options = options.unwrap_or_default();
+    options.script_path = options.script_path.or(Some(PathBuf::from(&file_path)));
+    exec_script(handle, script_content, Some(on_event), options).await
+}
+
+// Runs a script as a dry run and returns what it would have done
+pub async fn dry_run_plan(
+    handle: AppHandle,
+    script_content: String,
+    mut options: ScriptOptions,
+) -> Result<ScriptPlan, String> {
+    options.dry_run = true;
+    let json = exec_script(handle, script_content, None, options).await?;
+    let response: ScriptResponse = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    Ok(response.plan.unwrap_or_default())
 }
 
 #[tauri::command]
-pub fn get_all_commands() -> Vec<String> {
+pub fn get_all_commands(app: AppHandle) -> Vec<String> {
+    let downloads = match resolve_appdata_path(&app) {
+        Ok(app_data) => DownloadManager::new(DownloadCache::new(&app_data)),
+        Err(_) => DownloadManager::new(DownloadCache::at(std::env::temp_dir())),
+    };
     let mut context = Context::new();
     duckscriptsdk::load(&mut context.commands).unwrap();
+    let env = ScriptEnv::default().shared();
+    load_mud_commands(&mut context.commands, downloads, env).unwrap();
     context.commands.get_all_command_names()
 }
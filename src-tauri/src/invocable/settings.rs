@@ -1,36 +1,46 @@
 use crate::context::{forms, parser};
-use crate::invocable::git::check_if_git;
+use crate::invocable::git::{check_if_git, COMMUNITY_REPO_PATH};
+use crate::invocable::history::record_snapshot;
+use crate::invocable::profiles::{load_profiles, save_profiles};
 use crate::utils::error_handler::AppError;
 use serde_json::json;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tauri::{AppHandle, Manager, Wry};
 use tauri_plugin_store::JsonValue;
 use tauri_plugin_store::Store;
 use tauri_plugin_store::StoreExt;
 
+pub static STORE_PATH: &str = "store.bin";
+pub static SETTINGS_SOURCE: &str = "community";
+pub static DEFAULT_GAME_ID: &str = "baldursGate3"; // stub
+
 #[tauri::command]
-pub async fn build_form_html(app: AppHandle) -> Result<String, AppError> {
+pub async fn build_form_html(app: AppHandle, game_id: Option<String>) -> Result<String, AppError> {
     let def_html = "Error: Unable to load form data\n";
-    let form_path = "games/baldursGate3/config.json".to_string(); // stub
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
 
-    let store = app.store("store.bin")?;
+    let store = app.store(STORE_PATH)?;
 
     let app_data_path = get_res_appdata_path(app, &store);
-    let repo_path = app_data_path.join("git");
+    let repo_path = app_data_path.join(COMMUNITY_REPO_PATH);
     let is_target_ws = check_if_git(&repo_path);
 
     let json_object = if is_target_ws {
-        let config_path = repo_path.join(&form_path);
+        let config_path = game_config_path(&repo_path, &game_id);
         parser::load_json(config_path).map_err(|e| e.to_string())
     } else {
         Err(def_html.to_string())
     };
+    let game_template = game_template_path(&repo_path, &game_id);
 
     match json_object {
-        Ok(json) => Ok(forms::generate_form_html(json, None).unwrap_or(def_html.to_string())),
+        Ok(json) => {
+            Ok(forms::generate_form_html(json, Some(&game_template))
+                .unwrap_or(def_html.to_string()))
+        }
         Err(_e) => {
             Err(AppError::ParsingError(def_html.to_string()))
         }
@@ -38,24 +48,25 @@ pub async fn build_form_html(app: AppHandle) -> Result<String, AppError> {
 }
 
 #[tauri::command]
-pub async fn build_form_json(app: AppHandle) -> Result<JsonValue, AppError> {
+pub async fn build_form_json(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<JsonValue, AppError> {
     let def_json = json!({ "error": "Could not parse settings file. Try resyncing repo." });
-    let form_path = "games/baldursGate3/config.json".to_string(); // stub
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
 
-    let store = app.store("store.bin")?;
+    let store = app.store(STORE_PATH)?;
 
     let app_data_path = get_res_appdata_path(app, &store);
-    let repo_path = app_data_path.join("git");
+    let repo_path = app_data_path.join(COMMUNITY_REPO_PATH);
     let is_target_ws = check_if_git(&repo_path);
 
     if is_target_ws {
-        let config_path = repo_path.join(&form_path);
-        let hash_key = hash_path(&config_path);
+        let config_path = game_config_path(&repo_path, &game_id);
         match parser::load_json(config_path) {
             Ok(mut parsed_json) => {
-                if let Some(existing_data) = load_existing_data(&store, hash_key) {
-                    parsed_json = merge_form_data(parsed_json, existing_data);
-                }
+                let existing_data = load_profiles(&store, &game_id).active_values();
+                parsed_json = merge_form_data(parsed_json, JsonValue::Object(existing_data));
                 Ok(parsed_json)
             }
             Err(_e) => Err(AppError::ParsingError(def_json.to_string())),
@@ -66,18 +77,20 @@ pub async fn build_form_json(app: AppHandle) -> Result<JsonValue, AppError> {
 }
 
 #[tauri::command]
-pub async fn submit_form(app: AppHandle, form_data: JsonValue) -> Result<(), AppError> {
-    let store = app.store("store.bin")?;
-    let form_path = "games/baldursGate3/config.json".to_string(); // stub
-
-    let app_data_path = get_res_appdata_path(app, &store);
-    let repo_path = app_data_path.join("git");
-    let config_path = repo_path.join(&form_path);
-    let hash_key = hash_path(&config_path);
+pub async fn submit_form(
+    app: AppHandle,
+    form_data: JsonValue,
+    game_id: Option<String>,
+) -> Result<(), AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
 
-    if let Some(_map) = form_data.as_object() {
-        store.set(hash_key.to_string(), form_data);
-        store.save()?
+    if let Some(map) = form_data.as_object() {
+        // Writes into whichever profile is currently active
+        let mut game_profiles = load_profiles(&store, &game_id);
+        record_snapshot(&store, &game_id, &game_profiles.active, map)?;
+        game_profiles.set_active_values(map.clone());
+        save_profiles(&store, &game_id, &game_profiles)?
     } else {
         return Err(AppError::ParsingError(
             "Invalid form data format".to_string(),
@@ -86,7 +99,7 @@ pub async fn submit_form(app: AppHandle, form_data: JsonValue) -> Result<(), App
     Ok(())
 }
 
-fn get_res_appdata_path(app: AppHandle, store: &Arc<Store<Wry>>) -> PathBuf {
+pub fn get_res_appdata_path(app: AppHandle, store: &Arc<Store<Wry>>) -> PathBuf {
     let app_data_default = app.path().app_local_data_dir().unwrap();
 
     let app_data_dir = store
@@ -97,16 +110,148 @@ fn get_res_appdata_path(app: AppHandle, store: &Arc<Store<Wry>>) -> PathBuf {
     PathBuf::from(app_data_dir.replace("\"", ""))
 }
 
-// Using this to store our form data
-fn hash_path(path: &PathBuf) -> u64 {
+// App data path with the user's custom location applied
+pub fn resolve_appdata_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    let store = app.store(STORE_PATH)?;
+    Ok(get_res_appdata_path(app.clone(), &store))
+}
+
+// Local checkout of the community repo
+pub fn resolve_repo_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    Ok(resolve_appdata_path(app)?.join(COMMUNITY_REPO_PATH))
+}
+
+// Path to a game's settings schema within a synced repo
+pub fn game_config_path(repo_path: &Path, game_id: &str) -> PathBuf {
+    repo_path.join("games").join(game_id).join("config.json")
+}
+
+// Optional template a game folder can ship to override the default form
+pub fn game_template_path(repo_path: &Path, game_id: &str) -> PathBuf {
+    repo_path
+        .join("games")
+        .join(game_id)
+        .join(forms::GAME_TEMPLATE_FILE)
+}
+
+// Loads a game's settings schema from the synced community repo
+pub fn load_game_schema(
+    app: AppHandle,
+    store: &Arc<Store<Wry>>,
+    game_id: &str,
+) -> Result<JsonValue, AppError> {
+    let repo_path = get_res_appdata_path(app, store).join(COMMUNITY_REPO_PATH);
+    if !check_if_git(&repo_path) {
+        return Err(AppError::ParsingError(
+            "Community repo is not synced".to_string(),
+        ));
+    }
+    parser::load_json(game_config_path(&repo_path, game_id))
+        .map_err(|e| AppError::ParsingError(e.to_string()))
+}
+
+// Default value of every setting in a schema, keyed by setting key
+pub fn schema_defaults(schema: &JsonValue) -> serde_json::Map<String, JsonValue> {
+    schema
+        .get("settings")
+        .and_then(|s| s.as_array())
+        .map(|settings| {
+            settings
+                .iter()
+                .filter_map(|setting| {
+                    let key = setting.get("key")?.as_str()?;
+                    let default = setting.get("default")?;
+                    Some((key.to_string(), default.clone()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Checks saved values against the schema's keys and widget types
+pub fn validate_settings(schema: &JsonValue, values: &JsonValue) -> Result<(), AppError> {
+    let values = values
+        .as_object()
+        .ok_or_else(|| AppError::ParsingError("Settings must be an object".to_string()))?;
+    let settings = schema
+        .get("settings")
+        .and_then(|s| s.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    for (key, value) in values {
+        let setting = settings
+            .iter()
+            .find(|s| s.get("key").and_then(|k| k.as_str()) == Some(key.as_str()))
+            .ok_or_else(|| AppError::ParsingError(format!("Unknown setting '{}'", key)))?;
+
+        let valid = match setting.get("widget").and_then(|w| w.as_str()) {
+            Some("boolean") => value.is_boolean(),
+            Some("choice") => setting
+                .get("options")
+                .and_then(|o| o.as_array())
+                .map(|options| options.contains(value))
+                .unwrap_or(false),
+            Some("text") => value.is_string(),
+            _ => true,
+        };
+        if !valid {
+            return Err(AppError::ParsingError(format!(
+                "Invalid value {} for setting '{}'",
+                value, key
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Stable store key for a game's saved settings, e.g. "settings/community/baldursGate3"
+pub fn settings_key(source: &str, game_id: &str) -> String {
+    format!("settings/{}/{}", source, game_id)
+}
+
+// Legacy key scheme, only kept around for migrating old stores
+fn legacy_hash_path(path: &PathBuf) -> u64 {
     let mut hasher = DefaultHasher::new();
     path.hash(&mut hasher);
     hasher.finish()
 }
 
-// Load the form data if it exists
-fn load_existing_data(store: &Arc<Store<Wry>>, key: u64) -> Option<JsonValue> {
-    store.get(&key.to_string())
+/*
+Moves settings saved under the old path-hash keys over to stable source/game keys.
+Runs on startup; games are discovered from the synced repo since hashes can't be reversed.
+*/
+pub fn migrate_legacy_keys(app: &AppHandle) -> Result<usize, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let app_data_path = get_res_appdata_path(app.clone(), &store);
+    let repo_path = app_data_path.join(COMMUNITY_REPO_PATH);
+    let games_path = repo_path.join("games");
+
+    if !games_path.is_dir() {
+        return Ok(0);
+    }
+
+    let mut migrated = 0;
+    for entry in std::fs::read_dir(&games_path)? {
+        let entry = entry?;
+        let game_id = entry.file_name().to_string_lossy().to_string();
+        let legacy_key =
+            legacy_hash_path(&repo_path.join(format!("games/{}/config.json", game_id))).to_string();
+
+        if let Some(data) = store.get(&legacy_key) {
+            let key = settings_key(SETTINGS_SOURCE, &game_id);
+            if !store.has(&key) {
+                store.set(key, data);
+            }
+            store.delete(&legacy_key);
+            migrated += 1;
+        }
+    }
+
+    if migrated > 0 {
+        store.save()?;
+    }
+    Ok(migrated)
 }
 
 fn merge_form_data(mut form_json: JsonValue, existing_data: JsonValue) -> JsonValue {
@@ -0,0 +1,74 @@
+use crate::games::{GameSnapshot, SnapshotInfo, VerifyReport};
+use crate::invocable::deploy::load_deploy_config;
+use crate::invocable::settings::{resolve_appdata_path, DEFAULT_GAME_ID};
+use crate::utils::error_handler::AppError;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tokio::task;
+
+// Explicit folder first, then the one configured for deployment
+fn resolve_game_dir(
+    app: &AppHandle,
+    game_id: &str,
+    game_dir: Option<PathBuf>,
+) -> Result<PathBuf, AppError> {
+    let game_dir = match game_dir {
+        Some(game_dir) => game_dir,
+        None => load_deploy_config(app, game_id)?.game_dir.ok_or_else(|| {
+            AppError::InstallError(format!("No game folder is set for '{}'", game_id))
+        })?,
+    };
+    if !game_dir.is_dir() {
+        return Err(AppError::InstallError(format!(
+            "Game folder {:?} does not exist",
+            game_dir
+        )));
+    }
+    Ok(game_dir)
+}
+
+// Hashes the game folder as the baseline for later verification, replacing any older one
+#[tauri::command]
+pub async fn snapshot_game_dir(
+    app: AppHandle,
+    game_id: Option<String>,
+    game_dir: Option<PathBuf>,
+) -> Result<SnapshotInfo, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let game_dir = resolve_game_dir(&app, &game_id, game_dir)?;
+
+    // Hashing a whole game can take a while
+    task::spawn_blocking(move || {
+        let snapshot = GameSnapshot::capture(&game_dir)?;
+        snapshot.save(&app_data, &game_id)?;
+        Ok(snapshot.info())
+    })
+    .await
+    .map_err(|e| AppError::InstallError(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn get_game_snapshot(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<Option<SnapshotInfo>, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    Ok(GameSnapshot::load(&app_data, &game_id)?.map(|s| s.info()))
+}
+
+#[tauri::command]
+pub async fn verify_game_dir(
+    app: AppHandle,
+    game_id: Option<String>,
+) -> Result<VerifyReport, AppError> {
+    let app_data = resolve_appdata_path(&app)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let snapshot = GameSnapshot::load(&app_data, &game_id)?
+        .ok_or_else(|| AppError::InstallError(format!("No baseline snapshot for '{}'", game_id)))?;
+
+    task::spawn_blocking(move || snapshot.verify())
+        .await
+        .map_err(|e| AppError::InstallError(e.to_string()))?
+}
@@ -0,0 +1,185 @@
+use crate::invocable::profiles::{load_profiles, save_profiles, GameProfiles};
+use crate::invocable::settings::{
+    load_game_schema, validate_settings, DEFAULT_GAME_ID, SETTINGS_SOURCE, STORE_PATH,
+};
+use crate::utils::error_handler::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+pub static SETTINGS_EXPORT_VERSION: u32 = 1;
+
+// Portable settings file, written as JSON or TOML depending on the extension
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsExport {
+    pub version: u32,
+    pub source: String,
+    pub game_id: String,
+    pub active: String,
+    pub profiles: BTreeMap<String, Map<String, Value>>,
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("toml"))
+        .unwrap_or(false)
+}
+
+// TOML has no null, unset values are left out and come back as missing keys
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(without_nulls).collect()),
+        value => value.clone(),
+    }
+}
+
+pub fn write_export(path: &Path, export: &SettingsExport) -> Result<(), AppError> {
+    let content = if is_toml(path) {
+        let value =
+            serde_json::to_value(export).map_err(|e| AppError::ParsingError(e.to_string()))?;
+        toml::to_string_pretty(&without_nulls(&value)).map_err(|e| {
+            AppError::ParsingError(format!("Settings can't be exported as TOML: {}", e))
+        })?
+    } else {
+        serde_json::to_string_pretty(export).map_err(|e| AppError::ParsingError(e.to_string()))?
+    };
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+pub fn read_export(path: &Path) -> Result<SettingsExport, AppError> {
+    let content = std::fs::read_to_string(path)?;
+    let export: SettingsExport = if is_toml(path) {
+        toml::from_str(&content).map_err(|e| AppError::ParsingError(e.to_string()))?
+    } else {
+        serde_json::from_str(&content).map_err(|e| AppError::ParsingError(e.to_string()))?
+    };
+
+    if export.version > SETTINGS_EXPORT_VERSION {
+        return Err(AppError::ParsingError(format!(
+            "Unsupported settings export version {}",
+            export.version
+        )));
+    }
+    if export.source != SETTINGS_SOURCE {
+        return Err(AppError::ParsingError(format!(
+            "Settings were exported from source '{}', expected '{}'",
+            export.source, SETTINGS_SOURCE
+        )));
+    }
+    Ok(export)
+}
+
+#[tauri::command]
+pub async fn export_settings(
+    app: AppHandle,
+    path: PathBuf,
+    game_id: Option<String>,
+    include_profiles: Option<bool>,
+) -> Result<(), AppError> {
+    let store = app.store(STORE_PATH)?;
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let game_profiles = load_profiles(&store, &game_id);
+
+    // Without profiles, only the active one is exported
+    let profiles = if include_profiles.unwrap_or(false) {
+        game_profiles.profiles
+    } else {
+        let mut active = BTreeMap::new();
+        active.insert(game_profiles.active.clone(), game_profiles.active_values());
+        active
+    };
+
+    let export = SettingsExport {
+        version: SETTINGS_EXPORT_VERSION,
+        source: SETTINGS_SOURCE.to_string(),
+        game_id,
+        active: game_profiles.active,
+        profiles,
+    };
+    write_export(&path, &export)
+}
+
+#[tauri::command]
+pub async fn import_settings(
+    app: AppHandle,
+    path: PathBuf,
+    game_id: Option<String>,
+) -> Result<GameProfiles, AppError> {
+    let store = app.store(STORE_PATH)?;
+    let export = read_export(&path)?;
+    let game_id = game_id.unwrap_or(export.game_id.clone());
+
+    let schema = load_game_schema(app.clone(), &store, &game_id)?;
+    for (name, values) in &export.profiles {
+        validate_settings(&schema, &Value::Object(values.clone()))
+            .map_err(|e| AppError::ParsingError(format!("Profile '{}': {}", name, e)))?;
+    }
+
+    // Imported profiles replace same-named local ones, others are kept
+    let mut game_profiles = load_profiles(&store, &game_id);
+    for (name, values) in export.profiles {
+        game_profiles.profiles.insert(name, values);
+    }
+    if game_profiles.profiles.contains_key(&export.active) {
+        game_profiles.active = export.active;
+    }
+
+    save_profiles(&store, &game_id, &game_profiles)?;
+    Ok(game_profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::temp_dir;
+    use serde_json::json;
+
+    fn export() -> SettingsExport {
+        let values = json!({ "name": "x", "unset": null, "nested": { "a": 1, "b": null } });
+        let mut profiles = BTreeMap::new();
+        profiles.insert("default".to_string(), values.as_object().unwrap().clone());
+        SettingsExport {
+            version: SETTINGS_EXPORT_VERSION,
+            source: SETTINGS_SOURCE.to_string(),
+            game_id: "game".to_string(),
+            active: "default".to_string(),
+            profiles,
+        }
+    }
+
+    #[test]
+    fn toml_export_leaves_out_unset_values() {
+        let path = temp_dir("settings-toml").join("settings.toml");
+        write_export(&path, &export()).unwrap();
+        let imported = read_export(&path).unwrap();
+        assert_eq!(
+            Value::Object(imported.profiles["default"].clone()),
+            json!({ "name": "x", "nested": { "a": 1 } })
+        );
+
+        // JSON keeps them
+        let path = path.with_extension("json");
+        write_export(&path, &export()).unwrap();
+        let imported = read_export(&path).unwrap();
+        assert_eq!(imported.profiles["default"]["unset"], Value::Null);
+    }
+
+    #[test]
+    fn import_rejects_other_sources() {
+        let path = temp_dir("settings-source").join("settings.json");
+        let mut export = export();
+        export.source = "elsewhere".to_string();
+        write_export(&path, &export).unwrap();
+        assert!(matches!(read_export(&path), Err(AppError::ParsingError(_))));
+    }
+}
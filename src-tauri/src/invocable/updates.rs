@@ -0,0 +1,90 @@
+use crate::context::manifest::ModManifest;
+use crate::invocable::git::try_sync_repo;
+use crate::invocable::runner::{exec_script, PayloadEvent, ScriptOptions};
+use crate::invocable::settings::{resolve_appdata_path, resolve_repo_path, DEFAULT_GAME_ID};
+use crate::mods::{find_updates, load_catalog, plan_upgrade, Ledger, ModUpdate};
+use crate::utils::error_handler::AppError;
+use semver::Version;
+use std::collections::BTreeMap;
+use tauri::{ipc::Channel, AppHandle};
+
+// Installed mods of a game and their recorded versions
+fn installed_versions(
+    app: &AppHandle,
+    game_id: &str,
+) -> Result<BTreeMap<String, Option<Version>>, AppError> {
+    let ledger = Ledger::load(&resolve_appdata_path(app)?)?;
+    Ok(ledger
+        .for_game(game_id)
+        .into_iter()
+        .map(|record| {
+            let version = record.version.as_ref().and_then(|v| Version::parse(v).ok());
+            (record.mod_id.clone(), version)
+        })
+        .collect())
+}
+
+// Compares installed versions with the catalog, syncing the community repo first when asked
+#[tauri::command]
+pub async fn check_mod_updates(
+    app: AppHandle,
+    game_id: Option<String>,
+    sync: Option<bool>,
+) -> Result<Vec<ModUpdate>, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    if sync.unwrap_or(false) && !try_sync_repo(resolve_appdata_path(&app)?).await {
+        eprintln!("Failed to sync the community repo, checking against the local copy");
+    }
+    let catalog = load_catalog(&resolve_repo_path(&app)?, &game_id)?;
+    Ok(find_updates(&catalog, &installed_versions(&app, &game_id)?))
+}
+
+/*
+Upgrades the given mods, or every installed mod when none are given, to the
+newest versions the rest of the installed mods allow. Returns what was installed.
+*/
+#[tauri::command]
+pub async fn upgrade_mods(
+    app: AppHandle,
+    mod_ids: Option<Vec<String>>,
+    game_id: Option<String>,
+    on_event: Channel<PayloadEvent>,
+) -> Result<Vec<ModManifest>, AppError> {
+    let game_id = game_id.unwrap_or(DEFAULT_GAME_ID.to_string());
+    let catalog = load_catalog(&resolve_repo_path(&app)?, &game_id)?;
+    let installed = installed_versions(&app, &game_id)?;
+    let targets = mod_ids.unwrap_or_else(|| installed.keys().cloned().collect());
+    if let Some(missing) = targets.iter().find(|id| !installed.contains_key(*id)) {
+        return Err(AppError::ResolveError(format!(
+            "Mod '{}' is not installed",
+            missing
+        )));
+    }
+
+    let plan = plan_upgrade(&catalog, &installed, &targets)
+        .map_err(|e| AppError::ResolveError(e.to_string()))?;
+    for manifest in &plan {
+        let path = manifest.path.as_ref().ok_or_else(|| {
+            AppError::InstallError(format!("No mudfile found for '{}'", manifest.id))
+        })?;
+        let script = std::fs::read_to_string(path)?;
+        let options = ScriptOptions {
+            game_id: Some(game_id.clone()),
+            mod_id: Some(manifest.id.clone()),
+            mod_version: manifest.version.as_ref().map(|v| v.to_string()),
+            transactional: true,
+            script_path: Some(path.clone()),
+            ..Default::default()
+        };
+        exec_script(app.clone(), script, Some(on_event.clone()), options)
+            .await
+            .map_err(|e| {
+                AppError::InstallError(format!(
+                    "Failed to upgrade {}: {}",
+                    manifest.display_name(),
+                    e
+                ))
+            })?;
+    }
+    Ok(plan)
+}
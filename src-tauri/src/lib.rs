@@ -3,6 +3,10 @@ mod invocable;
 mod output;
 mod utils;
 mod cli;
+mod commands;
+mod mods;
+mod games;
+mod downloads;
 
 use tauri_plugin_cli::CliExt;
 use tokio::runtime::Runtime;
@@ -14,6 +18,11 @@ use tauri::Manager;
 pub fn run() {
     let builder = tauri::Builder::default()
         .setup(|app| {
+            // MIGRATE OLD HASH-KEYED SETTINGS
+            if let Err(e) = invocable::migrate_legacy_keys(app.handle()) {
+                eprintln!("Failed to migrate legacy settings: {}", e);
+            }
+
             // HANDLE CLI ARGUMENTS
             if let Ok(matches) = app.cli().matches() {
                 match (&matches.args.get("help"), &matches.args.get("file"), &matches.args.get("code")) {
@@ -23,12 +32,18 @@ pub fn run() {
                             Options:\n\
                             \t-c, --code <CODE>\tInline code to execute instead of a file\n\
                             \t-d, --display\t\tFlag to enable GUI interpretation using Mud's Repl interface\n\
+                            \t-n, --dry-run\t\tPrint the files, downloads and processes a script would touch\n\
+                            \t-p, --pack <FILE>\tInstall a mod pack archive\n\
                             \t-h, --help\t\tShow this help message and exit\n\n\
                             Positional Arguments:\n\
                             \t<FILE>\t\t\tFile path to execute. Overrides if -c is used."
                         );
                         std::process::exit(0);
                     },
+                    _ if matches.args.get("pack").map(|p| p.value.is_string()).unwrap_or(false) => {
+                        let runtime = Runtime::new().unwrap();
+                        runtime.block_on(cli::handle_pack_install(app.handle().clone(), matches));
+                    },
                     (_, Some(file_arg), Some(code_arg)) if file_arg.value.is_string() || code_arg.value.is_string() => {
                         let runtime = Runtime::new().unwrap();
                         if let Some(res) = runtime.block_on(cli::handle_cli_execution(app.handle().clone(), matches)) {
@@ -64,6 +79,63 @@ pub fn run() {
             invocable::build_form_html,
             invocable::build_form_json,
             invocable::submit_form,
+            invocable::list_profiles,
+            invocable::create_profile,
+            invocable::clone_profile,
+            invocable::rename_profile,
+            invocable::delete_profile,
+            invocable::activate_profile,
+            invocable::export_settings,
+            invocable::import_settings,
+            invocable::get_settings_history,
+            invocable::reset_setting,
+            invocable::reset_all_settings,
+            invocable::revert_settings,
+            invocable::list_installed_mods,
+            invocable::uninstall_mod,
+            invocable::rollback_install,
+            invocable::list_mods,
+            invocable::resolve_mods,
+            invocable::get_load_order,
+            invocable::sort_load_order,
+            invocable::set_load_order,
+            invocable::set_mod_enabled,
+            invocable::set_mod_locked,
+            invocable::add_load_rule,
+            invocable::remove_load_rule,
+            invocable::write_load_order,
+            invocable::list_conflicts,
+            invocable::set_file_winner,
+            invocable::set_mod_priority,
+            invocable::get_deploy_config,
+            invocable::set_deploy_config,
+            invocable::get_deployment,
+            invocable::deploy_mods,
+            invocable::purge_mods,
+            invocable::detect_game_dir,
+            invocable::detect_games,
+            invocable::snapshot_game_dir,
+            invocable::get_game_snapshot,
+            invocable::verify_game_dir,
+            invocable::download_files,
+            invocable::clear_download_cache,
+            invocable::get_offline_mode,
+            invocable::set_offline_mode,
+            invocable::prefetch_downloads,
+            invocable::import_download_bundle,
+            invocable::export_mod_pack,
+            invocable::import_mod_pack,
+            invocable::create_backup,
+            invocable::list_backups,
+            invocable::restore_backup,
+            invocable::prune_backups,
+            invocable::check_mod_updates,
+            invocable::upgrade_mods,
+            invocable::get_launch_config,
+            invocable::set_launch_config,
+            invocable::preview_launch,
+            invocable::launch_game,
+            invocable::get_playtime,
             cli::get_cli_script
         ]);
 
@@ -0,0 +1,44 @@
+use crate::context::manifest::{parse_manifest, ModManifest};
+use crate::utils::error_handler::AppError;
+use crate::utils::files::walk_files;
+use std::path::{Path, PathBuf};
+
+pub static MUDFILE_EXTENSION: &str = "mud";
+
+// Mudfiles for a game live under games/<game>/mods in the community repo
+pub fn mods_dir(repo_path: &Path, game_id: &str) -> PathBuf {
+    repo_path.join("games").join(game_id).join("mods")
+}
+
+// Every mudfile with a manifest header, skipping (and logging) ones that fail to parse
+pub fn load_catalog(repo_path: &Path, game_id: &str) -> Result<Vec<ModManifest>, AppError> {
+    let dir = mods_dir(repo_path, game_id);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut catalog = Vec::new();
+    for path in walk_files(&dir) {
+        if path
+            .extension()
+            .map(|e| e != MUDFILE_EXTENSION)
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)?;
+        match parse_manifest(&content) {
+            Ok(Some(mut manifest)) => {
+                manifest.path = Some(path);
+                catalog.push(manifest);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Skipping {:?}: {}", path, e),
+        }
+    }
+    catalog.sort_by(|a, b| {
+        a.id.cmp(&b.id)
+            .then(b.version_or_default().cmp(&a.version_or_default()))
+    });
+    Ok(catalog)
+}
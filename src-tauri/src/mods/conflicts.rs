@@ -0,0 +1,404 @@
+use crate::mods::ledger::{
+    backup_dir, backup_name, file_action, provided_dir, InstallRecord, Ledger, LEDGER_DIR,
+};
+use crate::utils::error_handler::AppError;
+use crate::utils::files::copy_file;
+use crate::utils::hashing::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub static CONFLICT_RULES_FILE: &str = "conflicts.json";
+
+/*
+How the user wants overlapping files resolved for a game. A per-file winner
+beats mod priority, and mods later in `priority` win over earlier ones.
+Without either, the mod installed last wins.
+*/
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConflictRules {
+    #[serde(default)]
+    pub priority: Vec<String>,
+    #[serde(default)]
+    pub files: BTreeMap<PathBuf, String>,
+}
+
+impl ConflictRules {
+    pub fn path(app_data: &Path, game_id: &str) -> PathBuf {
+        app_data
+            .join(LEDGER_DIR)
+            .join(game_id)
+            .join(CONFLICT_RULES_FILE)
+    }
+
+    pub fn load(app_data: &Path, game_id: &str) -> Result<Self, AppError> {
+        let path = Self::path(app_data, game_id);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| AppError::ParsingError(e.to_string()))
+    }
+
+    pub fn save(&self, app_data: &Path, game_id: &str) -> Result<(), AppError> {
+        let path = Self::path(app_data, game_id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::ParsingError(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    // Index of the mod that wins a file, the last one when no rule decides
+    pub fn winner_index(&self, path: &Path, mod_ids: &[&str]) -> Option<usize> {
+        if let Some(mod_id) = self.files.get(path) {
+            if let Some(index) = mod_ids.iter().position(|id| *id == mod_id.as_str()) {
+                return Some(index);
+            }
+        }
+        mod_ids
+            .iter()
+            .enumerate()
+            .max_by_key(|(index, mod_id)| {
+                let rank = self.priority.iter().position(|id| id.as_str() == **mod_id);
+                (rank.map(|r| r + 1).unwrap_or(0), *index)
+            })
+            .map(|(index, _)| index)
+    }
+
+    // Providers are in install order, so the last one is the default winner
+    fn winner<'a>(&self, path: &Path, providers: &'a [FileProvider]) -> Option<&'a FileProvider> {
+        let mod_ids: Vec<&str> = providers.iter().map(|p| p.mod_id.as_str()).collect();
+        self.winner_index(path, &mod_ids)
+            .map(|index| &providers[index])
+    }
+}
+
+// One mod's version of a file, `hash` is None when the mod deleted it
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProvider {
+    pub mod_id: String,
+    pub hash: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileConflict {
+    pub path: PathBuf,
+    pub providers: Vec<FileProvider>,
+    // Mod whose version is on disk right now, None if the file was changed by something else
+    pub current: Option<String>,
+    pub winner: String,
+}
+
+// Every game file written by more than one installed mod
+pub fn find_conflicts(ledger: &Ledger, rules: &ConflictRules, game_id: &str) -> Vec<FileConflict> {
+    let mut records = ledger.for_game(game_id);
+    records.sort_by_key(|r| r.installed_at);
+
+    let mut by_path: BTreeMap<PathBuf, Vec<FileProvider>> = BTreeMap::new();
+    for record in records {
+        for file in &record.files {
+            by_path
+                .entry(file.path.clone())
+                .or_default()
+                .push(FileProvider {
+                    mod_id: record.mod_id.clone(),
+                    hash: file.hash.clone(),
+                });
+        }
+    }
+
+    by_path
+        .into_iter()
+        .filter(|(_, providers)| providers.len() > 1)
+        .filter_map(|(path, providers)| {
+            let on_disk = if path.is_file() {
+                hash_file(&path).ok()
+            } else {
+                None
+            };
+            let current = providers
+                .iter()
+                .rev()
+                .find(|p| p.hash == on_disk)
+                .map(|p| p.mod_id.clone());
+            let winner = rules.winner(&path, &providers)?.mod_id.clone();
+            Some(FileConflict {
+                path,
+                providers,
+                current,
+                winner,
+            })
+        })
+        .collect()
+}
+
+// Where a mod keeps its backup of a file
+fn backup_path(app_data: &Path, game_id: &str, mod_id: &str, path: &Path) -> PathBuf {
+    backup_dir(app_data, game_id, mod_id).join(backup_name(path))
+}
+
+// Points a record's file at a new version under it, dropping it when the two match
+fn relink(
+    record: &mut InstallRecord,
+    path: &Path,
+    original_hash: Option<String>,
+    backup: Option<PathBuf>,
+) {
+    let index = match record.files.iter().position(|f| f.path == path) {
+        Some(index) => index,
+        None => return,
+    };
+    let file = &mut record.files[index];
+    file.original_hash = original_hash;
+    file.backup = backup;
+    match file_action(&file.hash, &file.original_hash) {
+        Some(action) => file.action = action,
+        None => {
+            record.files.remove(index);
+        }
+    }
+}
+
+/*
+Moves the winner's version of a file to the top of the installs that wrote it,
+so the ledger agrees with the disk once it's deployed. The install above the
+winner takes over its backup, and the winner backs up the version it replaces.
+*/
+fn raise_winner(
+    app_data: &Path,
+    ledger: &mut Ledger,
+    game_id: &str,
+    path: &Path,
+    winner: &str,
+) -> Result<(), AppError> {
+    let file = match ledger.find(game_id, winner).and_then(|r| r.file(path)) {
+        Some(file) => file.clone(),
+        None => return Ok(()),
+    };
+    let next = match ledger
+        .find(game_id, winner)
+        .and_then(|record| ledger.above(record, &file))
+    {
+        Some(next) => next.clone(),
+        None => return Ok(()), // Already on top
+    };
+    let mut top = next.clone();
+    // Bounded in case identical versions back each other up
+    for _ in 0..ledger.installs.len() {
+        match top.file(path).and_then(|f| ledger.above(&top, f)) {
+            Some(above) => top = above.clone(),
+            None => break,
+        }
+    }
+    let top_hash = top.file(path).and_then(|f| f.hash.clone());
+
+    // The winner's backup moves up a level before it's replaced
+    let next_backup = match &file.backup {
+        Some(backup) => {
+            let copy = backup_path(app_data, game_id, &next.mod_id, path);
+            copy_file(backup, &copy)?;
+            Some(copy)
+        }
+        None => None,
+    };
+    let winner_backup = match &top_hash {
+        Some(hash) => {
+            let copy = backup_path(app_data, game_id, winner, path);
+            // The top version is usually still on disk, the stored copy covers the rest
+            let source = match path.is_file() && hash_file(path).ok().as_ref() == Some(hash) {
+                true => path.to_path_buf(),
+                false => provided_dir(app_data, game_id, &top.mod_id).join(hash),
+            };
+            copy_file(&source, &copy)?;
+            Some(copy)
+        }
+        None => None,
+    };
+
+    if let Some(record) = ledger.find_mut(game_id, &next.mod_id) {
+        relink(record, path, file.original_hash.clone(), next_backup);
+    }
+    if let Some(record) = ledger.find_mut(game_id, winner) {
+        relink(record, path, top_hash, winner_backup);
+    }
+    Ok(())
+}
+
+/*
+Puts each conflict winner's version of the file in place and updates the
+ledger to match. Returns the paths that changed, skipping files whose winner
+has no stored copy to deploy.
+*/
+pub fn apply_conflicts(app_data: &Path, game_id: &str) -> Result<Vec<PathBuf>, AppError> {
+    let mut ledger = Ledger::load(app_data)?;
+    let rules = ConflictRules::load(app_data, game_id)?;
+
+    let mut changed = Vec::new();
+    for conflict in find_conflicts(&ledger, &rules, game_id) {
+        if conflict.current.as_ref() == Some(&conflict.winner) {
+            continue;
+        }
+        let winner = match conflict
+            .providers
+            .iter()
+            .rev()
+            .find(|p| p.mod_id == conflict.winner)
+        {
+            Some(winner) => winner,
+            None => continue,
+        };
+
+        let copy = winner
+            .hash
+            .as_ref()
+            .map(|hash| provided_dir(app_data, game_id, &winner.mod_id).join(hash));
+        if let Some(copy) = copy.as_ref().filter(|copy| !copy.is_file()) {
+            eprintln!(
+                "No stored copy {:?} of {:?} from '{}', reinstall it to redeploy",
+                copy, conflict.path, winner.mod_id
+            );
+            continue;
+        }
+        raise_winner(
+            app_data,
+            &mut ledger,
+            game_id,
+            &conflict.path,
+            &winner.mod_id,
+        )?;
+        match copy {
+            Some(copy) => {
+                copy_file(&copy, &conflict.path)?;
+            }
+            None => {
+                if conflict.path.is_file() {
+                    std::fs::remove_file(&conflict.path)?;
+                }
+            }
+        }
+        changed.push(conflict.path);
+    }
+    ledger.save(app_data)?;
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mods::ledger::tests::{install, read, write, GAME};
+    use crate::mods::uninstall::uninstall;
+    use crate::utils::testing::temp_dir;
+
+    fn set_winner(app_data: &Path, path: &Path, mod_id: &str) -> Vec<PathBuf> {
+        let mut rules = ConflictRules::load(app_data, GAME).unwrap();
+        rules.files.insert(path.to_path_buf(), mod_id.to_string());
+        rules.save(app_data, GAME).unwrap();
+        apply_conflicts(app_data, GAME).unwrap()
+    }
+
+    fn reasons(app_data: &Path, mod_id: &str) -> Vec<String> {
+        let report = uninstall(app_data, GAME, mod_id, false).unwrap();
+        assert!(!report.applied);
+        report.modified.into_iter().map(|m| m.reason).collect()
+    }
+
+    // Two mods over a vanilla file and a file only mods create
+    fn setup(name: &str) -> (PathBuf, PathBuf, PathBuf, PathBuf) {
+        let root = temp_dir(name);
+        let app_data = root.join("appdata");
+        let (data, added) = (root.join("game/data.pak"), root.join("game/Mods/added.pak"));
+        write(&data, "vanilla");
+        for mod_id in ["first", "second"] {
+            install(
+                &app_data,
+                mod_id,
+                "1.0.0",
+                &[(&data, Some(mod_id)), (&added, Some(mod_id))],
+            );
+        }
+        (root, app_data, data, added)
+    }
+
+    #[test]
+    fn flipped_winner_uninstalls_back_to_the_loser() {
+        let (root, app_data, data, added) = setup("conflicts-flip");
+        assert_eq!(set_winner(&app_data, &data, "first"), vec![data.clone()]);
+        assert_eq!(set_winner(&app_data, &added, "first"), vec![added.clone()]);
+        assert_eq!(read(&data).as_deref(), Some("first"));
+        let ledger = Ledger::load(&app_data).unwrap();
+        let rules = ConflictRules::load(&app_data, GAME).unwrap();
+        assert!(find_conflicts(&ledger, &rules, GAME)
+            .iter()
+            .all(|c| c.current.as_deref() == Some("first")));
+
+        // The loser sits under the winner now
+        assert_eq!(
+            reasons(&app_data, "second"),
+            vec!["also written by mod 'first'"; 2]
+        );
+
+        assert!(uninstall(&app_data, GAME, "first", false).unwrap().applied);
+        assert_eq!(read(&data).as_deref(), Some("second"));
+        assert_eq!(read(&added).as_deref(), Some("second"));
+
+        assert!(uninstall(&app_data, GAME, "second", false).unwrap().applied);
+        assert_eq!(read(&data).as_deref(), Some("vanilla"));
+        assert!(!added.exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn flipped_back_winner_uninstalls_in_install_order() {
+        let (root, app_data, data, added) = setup("conflicts-flip-back");
+        set_winner(&app_data, &data, "first");
+        set_winner(&app_data, &data, "second");
+        assert_eq!(read(&data).as_deref(), Some("second"));
+        // Already on top, nothing to redeploy
+        assert!(set_winner(&app_data, &added, "second").is_empty());
+
+        assert_eq!(
+            reasons(&app_data, "first"),
+            vec!["also written by mod 'second'"; 2]
+        );
+
+        assert!(uninstall(&app_data, GAME, "second", false).unwrap().applied);
+        assert_eq!(read(&data).as_deref(), Some("first"));
+        assert_eq!(read(&added).as_deref(), Some("first"));
+
+        assert!(uninstall(&app_data, GAME, "first", false).unwrap().applied);
+        assert_eq!(read(&data).as_deref(), Some("vanilla"));
+        assert!(!added.exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn later_install_goes_under_the_winner() {
+        let (root, app_data, data, added) = setup("conflicts-later");
+        set_winner(&app_data, &data, "first");
+        set_winner(&app_data, &added, "first");
+        install(&app_data, "third", "1.0.0", &[(&data, Some("third"))]);
+        assert_eq!(
+            apply_conflicts(&app_data, GAME).unwrap(),
+            vec![data.clone()]
+        );
+        assert_eq!(read(&data).as_deref(), Some("first"));
+
+        for (mod_id, left) in [
+            ("first", "third"),
+            ("third", "second"),
+            ("second", "vanilla"),
+        ] {
+            assert!(uninstall(&app_data, GAME, mod_id, false).unwrap().applied);
+            assert_eq!(read(&data).as_deref(), Some(left));
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,487 @@
+use crate::mods::conflicts::ConflictRules;
+use crate::utils::error_handler::AppError;
+use crate::utils::files::{copy_file, walk_files};
+use crate::utils::hashing::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub static STAGING_DIR: &str = "staging";
+pub static DEPLOYMENT_FILE: &str = "deployment.json";
+
+// How many files deploy places between saves of deployment.json
+const DEPLOY_SAVE_INTERVAL: usize = 256;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployMethod {
+    #[default]
+    Hardlink,
+    Symlink,
+    Copy,
+}
+
+// A file placed in the game folder, relative to the game folder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployedFile {
+    pub path: PathBuf,
+    pub mod_id: String,
+    pub method: DeployMethod,
+    // Set when a vanilla file was moved aside to make room
+    pub vanilla: Option<PathBuf>,
+}
+
+// What is currently deployed for a game, persisted so it can be purged later
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    pub game_dir: PathBuf,
+    pub files: Vec<DeployedFile>,
+    // Directories deployment had to create, removed again on purge when empty
+    #[serde(default)]
+    pub created_dirs: Vec<PathBuf>,
+    // Still set when a deploy was interrupted, files placed after the last save aren't listed
+    // and purge finds them through the staged mods instead
+    #[serde(default)]
+    pub in_progress: bool,
+}
+
+/*
+Staging layout, all under <appdata>/staging/<game>:
+mods/<mod>/...   files a mod provides, laid out like the game folder
+vanilla/...      game files moved aside while a mod's version is deployed
+deployment.json  the current Deployment
+*/
+pub fn staging_root(app_data: &Path, game_id: &str) -> PathBuf {
+    app_data.join(STAGING_DIR).join(game_id)
+}
+
+pub fn mod_staging_dir(app_data: &Path, game_id: &str, mod_id: &str) -> PathBuf {
+    staging_root(app_data, game_id).join("mods").join(mod_id)
+}
+
+fn vanilla_dir(app_data: &Path, game_id: &str) -> PathBuf {
+    staging_root(app_data, game_id).join("vanilla")
+}
+
+// Mods with anything staged, by id
+pub fn staged_mods(app_data: &Path, game_id: &str) -> Vec<String> {
+    let mut mods: Vec<String> = std::fs::read_dir(staging_root(app_data, game_id).join("mods"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    mods.sort();
+    mods
+}
+
+impl Deployment {
+    pub fn path(app_data: &Path, game_id: &str) -> PathBuf {
+        staging_root(app_data, game_id).join(DEPLOYMENT_FILE)
+    }
+
+    pub fn load(app_data: &Path, game_id: &str) -> Result<Option<Self>, AppError> {
+        let path = Self::path(app_data, game_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| AppError::ParsingError(e.to_string()))
+    }
+
+    pub fn save(&self, app_data: &Path, game_id: &str) -> Result<(), AppError> {
+        let path = Self::path(app_data, game_id);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::ParsingError(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+// Links or copies one staged file, falling back to a copy when links aren't possible
+fn place_file(source: &Path, target: &Path, method: DeployMethod) -> io::Result<DeployMethod> {
+    let linked = match method {
+        DeployMethod::Hardlink => std::fs::hard_link(source, target),
+        #[cfg(unix)]
+        DeployMethod::Symlink => std::os::unix::fs::symlink(source, target),
+        #[cfg(windows)]
+        DeployMethod::Symlink => std::os::windows::fs::symlink_file(source, target),
+        DeployMethod::Copy => return copy_file(source, target).map(|_| DeployMethod::Copy),
+    };
+    match linked {
+        Ok(()) => Ok(method),
+        Err(e) => {
+            // e.g. staging and game folder on different drives
+            eprintln!("Could not link {:?} ({}), copying instead", target, e);
+            copy_file(source, target)?;
+            Ok(DeployMethod::Copy)
+        }
+    }
+}
+
+fn remove_deployed(target: &Path) -> io::Result<()> {
+    // symlink_metadata so dangling symlinks are removed too
+    if target.symlink_metadata().is_ok() {
+        std::fs::remove_file(target)?;
+    }
+    Ok(())
+}
+
+// Creates a directory and each missing parent, remembering which ones were new
+fn create_dirs(dir: &Path, created: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut missing = Vec::new();
+    let mut current = Some(dir);
+    while let Some(path) = current {
+        if path.exists() {
+            break;
+        }
+        missing.push(path.to_path_buf());
+        current = path.parent();
+    }
+    for path in missing.into_iter().rev() {
+        std::fs::create_dir(&path)?;
+        created.push(path);
+    }
+    Ok(())
+}
+
+/*
+Deploys staged mods into the game folder in the given order. When two mods
+provide the same file the game's conflict rules pick the winner, otherwise
+the later mod wins. Anything deployed before is purged first.
+*/
+pub fn deploy(
+    app_data: &Path,
+    game_id: &str,
+    game_dir: &Path,
+    mod_ids: &[String],
+    rules: &ConflictRules,
+    method: DeployMethod,
+) -> Result<Deployment, AppError> {
+    purge(app_data, game_id)?;
+
+    let mut deployment = Deployment {
+        game_dir: game_dir.to_path_buf(),
+        in_progress: true,
+        ..Default::default()
+    };
+    deployment.save(app_data, game_id)?;
+
+    let result = place_mods(app_data, game_id, mod_ids, rules, method, &mut deployment);
+    // Saved on failure too, so everything placed so far can still be purged
+    deployment.in_progress = result.is_err();
+    deployment.save(app_data, game_id)?;
+    result.map(|_| deployment)
+}
+
+fn place_mods(
+    app_data: &Path,
+    game_id: &str,
+    mod_ids: &[String],
+    rules: &ConflictRules,
+    method: DeployMethod,
+    deployment: &mut Deployment,
+) -> Result<(), AppError> {
+    let vanilla = vanilla_dir(app_data, game_id);
+    let game_dir = deployment.game_dir.clone();
+
+    // Every staged path with the mods providing it, in deploy order
+    let mut providers: Vec<(PathBuf, Vec<&str>)> = Vec::new();
+    let mut positions: HashMap<PathBuf, usize> = HashMap::new();
+    for mod_id in mod_ids {
+        let staged = mod_staging_dir(app_data, game_id, mod_id);
+        for source in walk_files(&staged) {
+            let relative = match source.strip_prefix(&staged) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue,
+            };
+            match positions.get(&relative) {
+                Some(&index) => providers[index].1.push(mod_id),
+                None => {
+                    positions.insert(relative.clone(), providers.len());
+                    providers.push((relative, vec![mod_id]));
+                }
+            }
+        }
+    }
+
+    for (relative, mods) in providers {
+        let target = game_dir.join(&relative);
+        let Some(index) = rules.winner_index(&target, &mods) else {
+            continue;
+        };
+        let mod_id = mods[index];
+        let source = mod_staging_dir(app_data, game_id, mod_id).join(&relative);
+
+        let mut backup = None;
+        if target.symlink_metadata().is_ok() {
+            let moved = vanilla.join(&relative);
+            if let Some(parent) = moved.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if std::fs::rename(&target, &moved).is_err() {
+                copy_file(&target, &moved)?;
+                std::fs::remove_file(&target)?;
+            }
+            backup = Some(moved);
+        }
+
+        if let Some(parent) = target.parent() {
+            create_dirs(parent, &mut deployment.created_dirs)?;
+        }
+        let method = place_file(&source, &target, method)?;
+        deployment.files.push(DeployedFile {
+            path: relative,
+            mod_id: mod_id.to_string(),
+            method,
+            vanilla: backup,
+        });
+        // Save now and then so a crash part way through loses little
+        if deployment.files.len().is_multiple_of(DEPLOY_SAVE_INTERVAL) {
+            deployment.save(app_data, game_id)?;
+        }
+    }
+    Ok(())
+}
+
+// Whether a game file is still the copy or link of a staged file
+fn is_placed_copy(target: &Path, source: &Path) -> bool {
+    match (hash_file(target), hash_file(source)) {
+        (Ok(placed), Ok(staged)) => placed == staged,
+        _ => false,
+    }
+}
+
+// Removes every deployed file and puts vanilla files back, returning the paths touched
+pub fn purge(app_data: &Path, game_id: &str) -> Result<Vec<PathBuf>, AppError> {
+    let deployment = match Deployment::load(app_data, game_id)? {
+        Some(deployment) => deployment,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut touched = Vec::new();
+    for file in &deployment.files {
+        let target = deployment.game_dir.join(&file.path);
+        remove_deployed(&target)?;
+        if let Some(vanilla) = &file.vanilla {
+            if std::fs::rename(vanilla, &target).is_err() {
+                copy_file(vanilla, &target)?;
+                std::fs::remove_file(vanilla)?;
+            }
+        }
+        touched.push(target);
+    }
+    // An interrupted deploy may have moved vanilla files aside without listing them
+    let vanilla = vanilla_dir(app_data, game_id);
+    if deployment.in_progress {
+        // Files it placed after the last save are found through the staged mods
+        let listed: HashSet<&Path> = deployment.files.iter().map(|f| f.path.as_path()).collect();
+        for mod_id in staged_mods(app_data, game_id) {
+            let staged = mod_staging_dir(app_data, game_id, &mod_id);
+            for source in walk_files(&staged) {
+                let relative = match source.strip_prefix(&staged) {
+                    Ok(relative) => relative,
+                    Err(_) => continue,
+                };
+                let target = deployment.game_dir.join(relative);
+                // Ones with a vanilla file moved aside are put back below
+                if listed.contains(relative)
+                    || vanilla.join(relative).exists()
+                    || !is_placed_copy(&target, &source)
+                {
+                    continue;
+                }
+                remove_deployed(&target)?;
+                // Directories created for it weren't listed either
+                let mut dir = target.parent();
+                while let Some(path) = dir.filter(|d| *d != deployment.game_dir) {
+                    if std::fs::remove_dir(path).is_err() {
+                        break;
+                    }
+                    dir = path.parent();
+                }
+                touched.push(target);
+            }
+        }
+        for moved in walk_files(&vanilla) {
+            if let Ok(relative) = moved.strip_prefix(&vanilla) {
+                let target = deployment.game_dir.join(relative);
+                remove_deployed(&target)?;
+                if std::fs::rename(&moved, &target).is_err() {
+                    copy_file(&moved, &target)?;
+                }
+                touched.push(target);
+            }
+        }
+    }
+    // Deepest first, only ones that ended up empty
+    for dir in deployment.created_dirs.iter().rev() {
+        let _ = std::fs::remove_dir(dir);
+    }
+
+    std::fs::remove_file(Deployment::path(app_data, game_id))?;
+    if vanilla.exists() {
+        std::fs::remove_dir_all(vanilla)?;
+    }
+    Ok(touched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mods::ledger::tests::{read, write, GAME};
+    use crate::utils::testing::temp_dir;
+
+    // App data with staged mods "one" (a.txt, sub/b.txt) and "two" (a.txt), and a game folder
+    fn setup(name: &str) -> (PathBuf, PathBuf) {
+        let root = temp_dir(name);
+        let app_data = root.join("appdata");
+        let game_dir = root.join("game");
+        write(&game_dir.join("a.txt"), "vanilla");
+        write(&game_dir.join("keep.txt"), "keep");
+        write(
+            &mod_staging_dir(&app_data, GAME, "one").join("a.txt"),
+            "one",
+        );
+        write(
+            &mod_staging_dir(&app_data, GAME, "one").join("sub/b.txt"),
+            "b",
+        );
+        write(
+            &mod_staging_dir(&app_data, GAME, "two").join("a.txt"),
+            "two",
+        );
+        (app_data, game_dir)
+    }
+
+    fn order() -> Vec<String> {
+        vec!["one".to_string(), "two".to_string()]
+    }
+
+    fn assert_vanilla(game_dir: &Path) {
+        assert_eq!(read(&game_dir.join("a.txt")).as_deref(), Some("vanilla"));
+        assert_eq!(read(&game_dir.join("keep.txt")).as_deref(), Some("keep"));
+        assert!(!game_dir.join("sub").exists());
+    }
+
+    #[test]
+    fn conflict_rules_pick_the_deployed_file() {
+        let (app_data, game_dir) = setup("deploy-rules");
+        let a = game_dir.join("a.txt");
+        let mut rules = ConflictRules::default();
+
+        let deployment = deploy(
+            &app_data,
+            GAME,
+            &game_dir,
+            &order(),
+            &rules,
+            DeployMethod::Copy,
+        )
+        .unwrap();
+        assert_eq!(read(&a).as_deref(), Some("two"));
+        assert_eq!(read(&game_dir.join("sub/b.txt")).as_deref(), Some("b"));
+        assert_eq!(deployment.files.len(), 2);
+
+        rules.priority = vec!["two".to_string(), "one".to_string()];
+        deploy(
+            &app_data,
+            GAME,
+            &game_dir,
+            &order(),
+            &rules,
+            DeployMethod::Copy,
+        )
+        .unwrap();
+        assert_eq!(read(&a).as_deref(), Some("one"));
+
+        rules.files.insert(a.clone(), "two".to_string());
+        let deployment = deploy(
+            &app_data,
+            GAME,
+            &game_dir,
+            &order(),
+            &rules,
+            DeployMethod::Hardlink,
+        )
+        .unwrap();
+        assert_eq!(read(&a).as_deref(), Some("two"));
+        let file = deployment
+            .files
+            .iter()
+            .find(|f| f.path == Path::new("a.txt"))
+            .unwrap();
+        assert_eq!(file.mod_id, "two");
+        assert!(file.vanilla.is_some());
+
+        let touched = purge(&app_data, GAME).unwrap();
+        assert_eq!(touched.len(), 2);
+        assert_vanilla(&game_dir);
+        assert!(Deployment::load(&app_data, GAME).unwrap().is_none());
+        // Staged files stay for the next deploy
+        assert!(mod_staging_dir(&app_data, GAME, "one")
+            .join("a.txt")
+            .exists());
+    }
+
+    #[test]
+    fn purge_recovers_files_an_interrupted_deploy_never_listed() {
+        let (app_data, game_dir) = setup("deploy-interrupted");
+        let rules = ConflictRules::default();
+        deploy(
+            &app_data,
+            GAME,
+            &game_dir,
+            &order(),
+            &rules,
+            DeployMethod::Hardlink,
+        )
+        .unwrap();
+
+        // As saved before the crash: nothing listed yet
+        let mut deployment = Deployment::load(&app_data, GAME).unwrap().unwrap();
+        deployment.files.clear();
+        deployment.created_dirs.clear();
+        deployment.in_progress = true;
+        deployment.save(&app_data, GAME).unwrap();
+        // Left alone even though it matches nothing the mods stage
+        write(&game_dir.join("sub/other.txt"), "other");
+
+        purge(&app_data, GAME).unwrap();
+        assert_eq!(read(&game_dir.join("a.txt")).as_deref(), Some("vanilla"));
+        assert_eq!(read(&game_dir.join("keep.txt")).as_deref(), Some("keep"));
+        assert!(!game_dir.join("sub/b.txt").exists());
+        assert_eq!(
+            read(&game_dir.join("sub/other.txt")).as_deref(),
+            Some("other")
+        );
+
+        std::fs::remove_file(game_dir.join("sub/other.txt")).unwrap();
+        deploy(
+            &app_data,
+            GAME,
+            &game_dir,
+            &order(),
+            &rules,
+            DeployMethod::Copy,
+        )
+        .unwrap();
+        let mut deployment = Deployment::load(&app_data, GAME).unwrap().unwrap();
+        deployment.files.retain(|f| f.path == Path::new("a.txt"));
+        deployment.created_dirs.clear();
+        deployment.in_progress = true;
+        deployment.save(&app_data, GAME).unwrap();
+
+        purge(&app_data, GAME).unwrap();
+        assert_vanilla(&game_dir);
+    }
+}
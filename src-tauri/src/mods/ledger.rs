@@ -0,0 +1,681 @@
+use crate::utils::error_handler::AppError;
+use crate::utils::files::{copy_file, walk_files};
+use crate::utils::hashing::{hash_bytes, hash_file};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub static LEDGER_DIR: &str = "installed";
+pub static LEDGER_FILE: &str = "ledger.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileAction {
+    Created,
+    Overwritten,
+    Deleted,
+}
+
+// A single file a mod installation touched
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRecord {
+    pub path: PathBuf,
+    pub action: FileAction,
+    pub hash: Option<String>,
+    pub original_hash: Option<String>,
+    pub backup: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallRecord {
+    pub game_id: String,
+    pub mod_id: String,
+    pub version: Option<String>,
+    pub installed_at: u64,
+    pub files: Vec<FileRecord>,
+    // Changes made by the most recent run only, used for rollback
+    #[serde(default)]
+    pub last_run: Vec<FileRecord>,
+    #[serde(default)]
+    pub previous_version: Option<String>,
+    #[serde(default)]
+    pub previous_installed_at: Option<u64>,
+}
+
+impl InstallRecord {
+    pub fn file(&self, path: &Path) -> Option<&FileRecord> {
+        self.files.iter().find(|f| f.path == path)
+    }
+}
+
+// Every installed mod, persisted as json under <appdata>/installed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    pub installs: Vec<InstallRecord>,
+}
+
+impl Ledger {
+    pub fn path(app_data: &Path) -> PathBuf {
+        app_data.join(LEDGER_DIR).join(LEDGER_FILE)
+    }
+
+    pub fn load(app_data: &Path) -> Result<Self, AppError> {
+        let path = Self::path(app_data);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| AppError::ParsingError(e.to_string()))
+    }
+
+    pub fn save(&self, app_data: &Path) -> Result<(), AppError> {
+        let path = Self::path(app_data);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::ParsingError(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn find(&self, game_id: &str, mod_id: &str) -> Option<&InstallRecord> {
+        self.installs
+            .iter()
+            .find(|r| r.game_id == game_id && r.mod_id == mod_id)
+    }
+
+    pub fn find_mut(&mut self, game_id: &str, mod_id: &str) -> Option<&mut InstallRecord> {
+        self.installs
+            .iter_mut()
+            .find(|r| r.game_id == game_id && r.mod_id == mod_id)
+    }
+
+    /*
+    The install directly above a record's version of a file, the one whose backup
+    holds that version. Installs stack in the order they ran, conflict winners
+    are moved to the top.
+    */
+    pub fn above(&self, record: &InstallRecord, file: &FileRecord) -> Option<&InstallRecord> {
+        // Installs in the same second are told apart by their place in the ledger
+        let rank = |r: &InstallRecord| {
+            let index = self
+                .installs
+                .iter()
+                .position(|i| i.game_id == r.game_id && i.mod_id == r.mod_id);
+            (r.installed_at, index)
+        };
+        self.installs.iter().find(|other| {
+            if other.game_id != record.game_id || other.mod_id == record.mod_id {
+                return false;
+            }
+            match other.file(&file.path) {
+                // Versions that back each other up, e.g. two deletes, go by install order
+                Some(theirs) => {
+                    theirs.original_hash == file.hash
+                        && (theirs.hash != file.original_hash || rank(other) > rank(record))
+                }
+                None => false,
+            }
+        })
+    }
+
+    pub fn for_game(&self, game_id: &str) -> Vec<&InstallRecord> {
+        self.installs
+            .iter()
+            .filter(|r| r.game_id == game_id)
+            .collect()
+    }
+
+    pub fn remove(&mut self, game_id: &str, mod_id: &str) -> Option<InstallRecord> {
+        let index = self
+            .installs
+            .iter()
+            .position(|r| r.game_id == game_id && r.mod_id == mod_id)?;
+        Some(self.installs.remove(index))
+    }
+
+    /*
+    Adds or replaces a mod's record. When reinstalling, files the mod already
+    touched keep their original state so uninstalling still restores vanilla.
+    */
+    pub fn upsert(&mut self, mut record: InstallRecord) {
+        if let Some(previous) = self.remove(&record.game_id, &record.mod_id) {
+            record.previous_version = previous.version.clone();
+            record.previous_installed_at = Some(previous.installed_at);
+            let mut files = Vec::new();
+            for mut file in std::mem::take(&mut record.files) {
+                if let Some(old) = previous.file(&file.path) {
+                    file.original_hash = old.original_hash.clone();
+                    file.backup = old.backup.clone();
+                    match file_action(&file.hash, &file.original_hash) {
+                        Some(action) => file.action = action,
+                        None => continue, // Back to its original state
+                    }
+                }
+                files.push(file);
+            }
+            for mut old in previous.files {
+                if files.iter().any(|f| f.path == old.path) {
+                    continue;
+                }
+                // Touched again but not listed, e.g. removed by this run
+                if let Some(run) = record.last_run.iter().find(|f| f.path == old.path) {
+                    old.hash = run.hash.clone();
+                    match file_action(&old.hash, &old.original_hash) {
+                        Some(action) => old.action = action,
+                        None => continue,
+                    }
+                }
+                files.push(old);
+            }
+            record.files = files;
+        }
+        self.installs.push(record);
+    }
+}
+
+// Backups are named after the path they were taken from
+pub fn backup_name(path: &Path) -> String {
+    hash_bytes(path.to_string_lossy().as_bytes())
+}
+
+pub fn backup_dir(app_data: &Path, game_id: &str, mod_id: &str) -> PathBuf {
+    app_data
+        .join(LEDGER_DIR)
+        .join(game_id)
+        .join(mod_id)
+        .join("backups")
+}
+
+// Copies of the files a mod wrote, by hash, so conflict winners can be redeployed
+pub fn provided_dir(app_data: &Path, game_id: &str, mod_id: &str) -> PathBuf {
+    app_data
+        .join(LEDGER_DIR)
+        .join(game_id)
+        .join(mod_id)
+        .join("provided")
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn run_backup_dir(app_data: &Path, game_id: &str, mod_id: &str, run: u64) -> PathBuf {
+    app_data
+        .join(LEDGER_DIR)
+        .join(game_id)
+        .join(mod_id)
+        .join("runs")
+        .join(run.to_string())
+}
+
+// State of a path before the script first touched it
+#[derive(Clone, Debug, Default)]
+struct Original {
+    // Relative to vanilla, only known the first time a mod touches a file
+    hash: Option<String>,
+    backup: Option<PathBuf>,
+    // Relative to the start of this run
+    run_hash: Option<String>,
+    run_backup: Option<PathBuf>,
+}
+
+/*
+Collects the files a single script run touches. File-writing commands call
+`track` before they run so the original contents can be backed up.
+*/
+pub struct InstallSession {
+    pub game_id: String,
+    pub mod_id: String,
+    pub started_at: u64,
+    backups: PathBuf,
+    run_backups: PathBuf,
+    known: HashSet<PathBuf>,
+    originals: BTreeMap<PathBuf, Original>,
+}
+
+pub type SharedSession = Arc<Mutex<InstallSession>>;
+
+impl InstallSession {
+    pub fn new(
+        app_data: &Path,
+        game_id: &str,
+        mod_id: &str,
+        previous: Option<&InstallRecord>,
+    ) -> Self {
+        let started_at = unix_now();
+        Self {
+            game_id: game_id.to_string(),
+            mod_id: mod_id.to_string(),
+            started_at,
+            backups: backup_dir(app_data, game_id, mod_id),
+            run_backups: run_backup_dir(app_data, game_id, mod_id, started_at),
+            known: previous
+                .map(|r| r.files.iter().map(|f| f.path.clone()).collect())
+                .unwrap_or_default(),
+            originals: BTreeMap::new(),
+        }
+    }
+
+    pub fn shared(self) -> SharedSession {
+        Arc::new(Mutex::new(self))
+    }
+
+    // Records the original state of a file, or of every file under a directory
+    pub fn track(&mut self, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            for file in walk_files(path) {
+                self.track_file(&file)?;
+            }
+            self.originals.entry(path.to_path_buf()).or_default();
+            Ok(())
+        } else {
+            self.track_file(path)
+        }
+    }
+
+    fn track_file(&mut self, path: &Path) -> io::Result<()> {
+        if self.originals.contains_key(path) {
+            return Ok(());
+        }
+
+        let mut original = Original::default();
+        if path.is_file() {
+            let name = backup_name(path);
+            let hash = hash_file(path)?;
+
+            let run_backup = self.run_backups.join(&name);
+            copy_file(path, &run_backup)?;
+            original.run_hash = Some(hash.clone());
+            original.run_backup = Some(run_backup);
+
+            // Files from an earlier install of this mod already have their backup
+            if !self.known.contains(path) {
+                let backup = self.backups.join(&name);
+                copy_file(path, &backup)?;
+                original.hash = Some(hash);
+                original.backup = Some(backup);
+            }
+        }
+        self.originals.insert(path.to_path_buf(), original);
+        Ok(())
+    }
+
+    // Every tracked file, expanding tracked directories
+    fn tracked_files(&self) -> Vec<(PathBuf, Original)> {
+        let mut files: Vec<(PathBuf, Original)> = Vec::new();
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        for (path, original) in &self.originals {
+            let paths = if path.is_dir() {
+                walk_files(path)
+            } else {
+                vec![path.clone()]
+            };
+            for file in paths {
+                if !seen.insert(file.clone()) {
+                    continue;
+                }
+                let original = self.originals.get(&file).unwrap_or(original);
+                files.push((file, original.clone()));
+            }
+        }
+        files
+    }
+
+    // Compares tracked paths against what is on disk now
+    pub fn finish(&self, version: Option<String>) -> InstallRecord {
+        let mut files = Vec::new();
+        let mut last_run = Vec::new();
+        for (file, original) in self.tracked_files() {
+            let hash = if file.is_file() {
+                hash_file(&file).ok()
+            } else {
+                None
+            };
+
+            if let Some(action) = file_action(&hash, &original.run_hash) {
+                last_run.push(FileRecord {
+                    path: file.clone(),
+                    action,
+                    hash: hash.clone(),
+                    original_hash: original.run_hash.clone(),
+                    backup: original.run_backup.clone(),
+                });
+            }
+            if let Some(action) = file_action(&hash, &original.hash) {
+                files.push(FileRecord {
+                    path: file,
+                    action,
+                    hash,
+                    original_hash: original.hash.clone(),
+                    backup: original.backup.clone(),
+                });
+            }
+        }
+
+        InstallRecord {
+            game_id: self.game_id.clone(),
+            mod_id: self.mod_id.clone(),
+            version,
+            installed_at: self.started_at,
+            files,
+            last_run,
+            previous_version: None,
+            previous_installed_at: None,
+        }
+    }
+
+    // Puts every file this run touched back the way it was, then drops the run's backups
+    pub fn rollback(&self) -> Result<Vec<PathBuf>, AppError> {
+        let mut restored = Vec::new();
+        for (file, original) in self.tracked_files() {
+            restore_file(&file, &original.run_backup)?;
+            restored.push(file);
+        }
+        if self.run_backups.exists() {
+            std::fs::remove_dir_all(&self.run_backups)?;
+        }
+        Ok(restored)
+    }
+}
+
+// How a file changed between two hashes, None when nothing changed
+pub fn file_action(hash: &Option<String>, original_hash: &Option<String>) -> Option<FileAction> {
+    match (hash, original_hash) {
+        (Some(h), Some(o)) if h == o => None,
+        (Some(_), Some(_)) => Some(FileAction::Overwritten),
+        (Some(_), None) => Some(FileAction::Created),
+        (None, Some(_)) => Some(FileAction::Deleted),
+        (None, None) => None,
+    }
+}
+
+// Restores a backup over a path, or removes the path when there was nothing before
+pub fn restore_file(path: &Path, backup: &Option<PathBuf>) -> Result<(), AppError> {
+    match backup {
+        Some(backup) => {
+            copy_file(backup, path)?;
+        }
+        None => {
+            if path.is_file() {
+                std::fs::remove_file(path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Finishes a session and writes its record into the ledger
+pub fn record_install(
+    app_data: &Path,
+    session: &SharedSession,
+    version: Option<String>,
+) -> Result<InstallRecord, AppError> {
+    let session = session
+        .lock()
+        .map_err(|e| AppError::InstallError(e.to_string()))?;
+    let record = session.finish(version);
+
+    let provided = provided_dir(app_data, &session.game_id, &session.mod_id);
+    for file in &record.files {
+        if let Some(hash) = &file.hash {
+            let copy = provided.join(hash);
+            if !copy.exists() && file.path.is_file() {
+                copy_file(&file.path, &copy)?;
+            }
+        }
+    }
+
+    let mut ledger = Ledger::load(app_data)?;
+    ledger.upsert(record.clone());
+    ledger.save(app_data)?;
+
+    // Only the latest run's backups are needed for rollback
+    let runs = run_backup_dir(app_data, &session.game_id, &session.mod_id, 0);
+    if let Some(runs) = runs.parent() {
+        for entry in std::fs::read_dir(runs).into_iter().flatten().flatten() {
+            if entry.file_name().to_string_lossy() != session.started_at.to_string() {
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+    Ok(record)
+}
+
+// Undoes a failed run before anything is written to the ledger
+pub fn rollback_session(session: &SharedSession) -> Result<Vec<PathBuf>, AppError> {
+    session
+        .lock()
+        .map_err(|e| AppError::InstallError(e.to_string()))?
+        .rollback()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::utils::testing::temp_dir;
+
+    pub const GAME: &str = "game";
+
+    pub fn write(path: &Path, contents: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    pub fn read(path: &Path) -> Option<String> {
+        std::fs::read_to_string(path).ok()
+    }
+
+    // Runs an install of the mod that writes each file, or deletes it for None, and records it
+    pub fn install(
+        app_data: &Path,
+        mod_id: &str,
+        version: &str,
+        changes: &[(&Path, Option<&str>)],
+    ) -> InstallRecord {
+        let session = start(app_data, mod_id, changes);
+        record_install(app_data, &session, Some(version.to_string())).unwrap()
+    }
+
+    // A session that made the changes but isn't recorded yet
+    pub fn start(
+        app_data: &Path,
+        mod_id: &str,
+        changes: &[(&Path, Option<&str>)],
+    ) -> SharedSession {
+        let ledger = Ledger::load(app_data).unwrap();
+        let session =
+            InstallSession::new(app_data, GAME, mod_id, ledger.find(GAME, mod_id)).shared();
+        for (path, contents) in changes {
+            session.lock().unwrap().track(path).unwrap();
+            match contents {
+                Some(contents) => write(path, contents),
+                None => std::fs::remove_file(path).unwrap(),
+            }
+        }
+        session
+    }
+
+    fn actions(files: &[FileRecord]) -> Vec<(PathBuf, FileAction)> {
+        let mut actions: Vec<_> = files
+            .iter()
+            .map(|f| (f.path.clone(), f.action.clone()))
+            .collect();
+        actions.sort_by(|a, b| a.0.cmp(&b.0));
+        actions
+    }
+
+    #[test]
+    fn file_action_compares_hashes() {
+        let hash = |h: &str| Some(h.to_string());
+        assert_eq!(file_action(&hash("a"), &hash("a")), None);
+        assert_eq!(
+            file_action(&hash("b"), &hash("a")),
+            Some(FileAction::Overwritten)
+        );
+        assert_eq!(file_action(&hash("a"), &None), Some(FileAction::Created));
+        assert_eq!(file_action(&None, &hash("a")), Some(FileAction::Deleted));
+        assert_eq!(file_action(&None, &None), None);
+    }
+
+    #[test]
+    fn session_records_changes_and_rolls_them_back() {
+        let root = temp_dir("ledger-session");
+        let (app_data, game) = (root.join("appdata"), root.join("game"));
+        let (overwritten, deleted, created) = (
+            game.join("data.pak"),
+            game.join("old.txt"),
+            game.join("Mods/new.pak"),
+        );
+        write(&overwritten, "vanilla");
+        write(&deleted, "remove me");
+
+        let session = start(
+            &app_data,
+            "mod",
+            &[
+                (&overwritten, Some("modded")),
+                (&deleted, None),
+                (&created, Some("new")),
+            ],
+        );
+        // Tracking the same file twice keeps the first original
+        session.lock().unwrap().track(&overwritten).unwrap();
+
+        let record = session.lock().unwrap().finish(Some("1.0.0".to_string()));
+        let expected = vec![
+            (created.clone(), FileAction::Created),
+            (overwritten.clone(), FileAction::Overwritten),
+            (deleted.clone(), FileAction::Deleted),
+        ];
+        assert_eq!(actions(&record.files), expected);
+        assert_eq!(actions(&record.last_run), expected);
+        let data = record.file(&overwritten).unwrap();
+        assert_eq!(data.original_hash, Some(hash_bytes(b"vanilla")));
+        assert_eq!(data.hash, Some(hash_bytes(b"modded")));
+        assert_eq!(
+            read(data.backup.as_ref().unwrap()).as_deref(),
+            Some("vanilla")
+        );
+        assert_eq!(record.file(&created).unwrap().backup, None);
+
+        let mut restored = rollback_session(&session).unwrap();
+        restored.sort();
+        assert_eq!(
+            restored,
+            vec![created.clone(), overwritten.clone(), deleted.clone()]
+        );
+        assert_eq!(read(&overwritten).as_deref(), Some("vanilla"));
+        assert_eq!(read(&deleted).as_deref(), Some("remove me"));
+        assert!(!created.exists());
+        let run = run_backup_dir(&app_data, GAME, "mod", session.lock().unwrap().started_at);
+        assert!(!run.exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn tracked_directories_pick_up_new_files() {
+        let root = temp_dir("ledger-directory");
+        let (app_data, mods) = (root.join("appdata"), root.join("game/Mods"));
+        write(&mods.join("kept.pak"), "kept");
+
+        let session = start(&app_data, "mod", &[]);
+        session.lock().unwrap().track(&mods).unwrap();
+        write(&mods.join("added.pak"), "added");
+        write(&mods.join("kept.pak"), "changed");
+
+        let record = session.lock().unwrap().finish(None);
+        assert_eq!(
+            actions(&record.files),
+            vec![
+                (mods.join("added.pak"), FileAction::Created),
+                (mods.join("kept.pak"), FileAction::Overwritten),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn record_install_keeps_vanilla_backups_and_prunes_old_runs() {
+        let root = temp_dir("ledger-record");
+        let (app_data, game) = (root.join("appdata"), root.join("game"));
+        let (data, extra) = (game.join("data.pak"), game.join("extra.pak"));
+        write(&data, "vanilla");
+
+        let first = install(&app_data, "mod", "1.0.0", &[(&data, Some("v1"))]);
+        let provided = provided_dir(&app_data, GAME, "mod");
+        assert_eq!(
+            read(&provided.join(hash_bytes(b"v1"))).as_deref(),
+            Some("v1")
+        );
+        // Stand-in for the backups of an earlier run
+        let stale = run_backup_dir(&app_data, GAME, "mod", first.installed_at - 1);
+        write(&stale.join("file"), "stale");
+
+        let second = install(
+            &app_data,
+            "mod",
+            "2.0.0",
+            &[(&data, Some("v2")), (&extra, Some("extra"))],
+        );
+        assert!(!stale.exists());
+        assert!(run_backup_dir(&app_data, GAME, "mod", second.installed_at).exists());
+
+        let ledger = Ledger::load(&app_data).unwrap();
+        let record = ledger.find(GAME, "mod").unwrap();
+        assert_eq!(record.version.as_deref(), Some("2.0.0"));
+        assert_eq!(record.previous_version.as_deref(), Some("1.0.0"));
+        // The reinstall overwrote v1, but the record still points at vanilla
+        let file = record.file(&data).unwrap();
+        assert_eq!(file.original_hash, Some(hash_bytes(b"vanilla")));
+        assert_eq!(
+            read(file.backup.as_ref().unwrap()).as_deref(),
+            Some("vanilla")
+        );
+        assert_eq!(
+            record
+                .last_run
+                .iter()
+                .find(|f| f.path == data)
+                .unwrap()
+                .original_hash,
+            Some(hash_bytes(b"v1"))
+        );
+        assert_eq!(record.file(&extra).unwrap().action, FileAction::Created);
+
+        // Writing vanilla back drops the file from the record
+        install(&app_data, "mod", "3.0.0", &[(&data, Some("vanilla"))]);
+        let ledger = Ledger::load(&app_data).unwrap();
+        assert!(ledger.find(GAME, "mod").unwrap().file(&data).is_none());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn restore_file_copies_the_backup_or_removes_the_file() {
+        let root = temp_dir("ledger-restore");
+        let (path, backup) = (root.join("file.txt"), root.join("backup"));
+        write(&path, "modded");
+        write(&backup, "vanilla");
+
+        restore_file(&path, &Some(backup)).unwrap();
+        assert_eq!(read(&path).as_deref(), Some("vanilla"));
+        restore_file(&path, &None).unwrap();
+        assert!(!path.exists());
+        // Nothing to remove is fine too
+        restore_file(&path, &None).unwrap();
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
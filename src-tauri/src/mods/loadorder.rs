@@ -0,0 +1,209 @@
+use crate::context::manifest::ModManifest;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadOrderEntry {
+    pub mod_id: String,
+    pub enabled: bool,
+    // Locked entries keep their position when sorting
+    #[serde(default)]
+    pub locked: bool,
+}
+
+// User rule that `before` must load ahead of `after`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoadRule {
+    pub before: String,
+    pub after: String,
+}
+
+// Persisted order for one game and profile
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LoadOrder {
+    pub entries: Vec<LoadOrderEntry>,
+    #[serde(default)]
+    pub rules: Vec<LoadRule>,
+}
+
+impl LoadOrder {
+    pub fn position(&self, mod_id: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.mod_id == mod_id)
+    }
+
+    // Adds newly installed mods at the end and drops ones that are gone
+    pub fn sync(&mut self, installed: &[String]) {
+        self.entries.retain(|e| installed.contains(&e.mod_id));
+        for mod_id in installed {
+            if self.position(mod_id).is_none() {
+                self.entries.push(LoadOrderEntry {
+                    mod_id: mod_id.clone(),
+                    enabled: true,
+                    locked: false,
+                });
+            }
+        }
+    }
+
+    // Edges (before, after) from manifest hints and user rules between known mods
+    fn edges(&self, manifests: &[ModManifest]) -> BTreeSet<(String, String)> {
+        let known: BTreeSet<&String> = self.entries.iter().map(|e| &e.mod_id).collect();
+        let mut edges = BTreeSet::new();
+
+        for manifest in manifests.iter().filter(|m| known.contains(&m.id)) {
+            for after in &manifest.load_after {
+                edges.insert((after.clone(), manifest.id.clone()));
+            }
+            for before in &manifest.load_before {
+                edges.insert((manifest.id.clone(), before.clone()));
+            }
+        }
+        for rule in &self.rules {
+            edges.insert((rule.before.clone(), rule.after.clone()));
+        }
+        edges.retain(|(a, b)| known.contains(a) && known.contains(b));
+        edges
+    }
+
+    /*
+    Sorts unlocked entries so every rule holds, otherwise keeping the current
+    order. Locked entries stay at their index. Fails with the mods in a cycle.
+    */
+    pub fn sort(&mut self, manifests: &[ModManifest]) -> Result<(), Vec<String>> {
+        let edges = self.edges(manifests);
+        let locked: Vec<(usize, LoadOrderEntry)> = self
+            .entries
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|(_, e)| e.locked)
+            .collect();
+        let mut pending: Vec<LoadOrderEntry> =
+            self.entries.iter().filter(|e| !e.locked).cloned().collect();
+
+        // Locked entries don't move, so rules touching them can't be enforced
+        let edges: BTreeSet<(String, String)> = edges
+            .into_iter()
+            .filter(|(a, b)| {
+                pending.iter().any(|e| &e.mod_id == a) && pending.iter().any(|e| &e.mod_id == b)
+            })
+            .collect();
+
+        let mut incoming: BTreeMap<String, usize> =
+            pending.iter().map(|e| (e.mod_id.clone(), 0)).collect();
+        for (_, after) in &edges {
+            if let Some(count) = incoming.get_mut(after) {
+                *count += 1;
+            }
+        }
+
+        let mut sorted = Vec::new();
+        while !pending.is_empty() {
+            // Earliest entry in the current order that has nothing left before it
+            let index = pending
+                .iter()
+                .position(|e| incoming.get(&e.mod_id).copied().unwrap_or(0) == 0)
+                .ok_or_else(|| pending.iter().map(|e| e.mod_id.clone()).collect::<Vec<_>>())?;
+            let entry = pending.remove(index);
+            for (before, after) in &edges {
+                if before == &entry.mod_id {
+                    if let Some(count) = incoming.get_mut(after) {
+                        *count -= 1;
+                    }
+                }
+            }
+            sorted.push(entry);
+        }
+
+        for (index, entry) in locked {
+            let index = index.min(sorted.len());
+            sorted.insert(index, entry);
+        }
+        self.entries = sorted;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub fn order(ids: &[&str]) -> LoadOrder {
+        LoadOrder {
+            entries: ids
+                .iter()
+                .map(|id| LoadOrderEntry {
+                    mod_id: id.to_string(),
+                    enabled: true,
+                    locked: false,
+                })
+                .collect(),
+            rules: Vec::new(),
+        }
+    }
+
+    fn rule(before: &str, after: &str) -> LoadRule {
+        LoadRule {
+            before: before.to_string(),
+            after: after.to_string(),
+        }
+    }
+
+    fn ids(order: &LoadOrder) -> Vec<&str> {
+        order.entries.iter().map(|e| e.mod_id.as_str()).collect()
+    }
+
+    #[test]
+    fn sync_appends_new_mods_and_drops_removed_ones() {
+        let mut order = order(&["a", "b", "c"]);
+        order.entries[2].enabled = false;
+        order.sync(&["c".to_string(), "d".to_string(), "a".to_string()]);
+        assert_eq!(ids(&order), ["a", "c", "d"]);
+        assert!(!order.entries[1].enabled);
+        assert!(order.entries[2].enabled);
+    }
+
+    #[test]
+    fn sort_follows_hints_and_rules_otherwise_keeping_the_order() {
+        let mut order = order(&["a", "b", "c", "d", "e"]);
+        order.rules.push(rule("d", "b"));
+        // Hints about mods that aren't in the order are ignored
+        let manifests = vec![ModManifest {
+            id: "c".to_string(),
+            load_before: vec!["a".to_string(), "missing".to_string()],
+            ..Default::default()
+        }];
+        order.sort(&manifests).unwrap();
+        assert_eq!(ids(&order), ["c", "a", "d", "b", "e"]);
+    }
+
+    #[test]
+    fn locked_entries_keep_their_index() {
+        let mut order = order(&["a", "b", "c", "d"]);
+        order.entries[1].locked = true;
+        order.rules.push(rule("c", "a"));
+        // Can't be enforced without moving the locked entry, so it's dropped
+        order.rules.push(rule("d", "b"));
+        order.sort(&[]).unwrap();
+        assert_eq!(ids(&order), ["c", "b", "a", "d"]);
+        assert!(order.entries[1].locked);
+    }
+
+    #[test]
+    fn sort_reports_the_mods_in_a_cycle() {
+        let mut order = order(&["a", "b", "c"]);
+        order.rules.push(rule("a", "b"));
+        let manifests = vec![ModManifest {
+            id: "a".to_string(),
+            load_after: vec!["b".to_string()],
+            ..Default::default()
+        }];
+        assert_eq!(
+            order.sort(&manifests),
+            Err(vec!["a".to_string(), "b".to_string()])
+        );
+        // Left untouched on failure
+        assert_eq!(ids(&order), ["a", "b", "c"]);
+    }
+}
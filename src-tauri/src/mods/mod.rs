@@ -0,0 +1,23 @@
+pub mod catalog;
+pub mod conflicts;
+pub mod deploy;
+pub mod ledger;
+pub mod loadorder;
+pub mod pack;
+pub mod resolver;
+pub mod transaction;
+pub mod uninstall;
+pub mod updates;
+pub mod writers;
+
+pub use catalog::*;
+pub use conflicts::*;
+pub use deploy::*;
+pub use ledger::*;
+pub use loadorder::*;
+pub use pack::*;
+pub use resolver::*;
+pub use transaction::*;
+pub use uninstall::*;
+pub use updates::*;
+pub use writers::*;
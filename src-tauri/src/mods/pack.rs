@@ -0,0 +1,121 @@
+use crate::mods::ledger::unix_now;
+use crate::mods::loadorder::LoadOrder;
+use crate::utils::archive::{create_zip, extract_archive, ArchiveFormat, ExtractOptions};
+use crate::utils::error_handler::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub static PACKS_DIR: &str = "packs";
+pub static PACK_MANIFEST: &str = "modpack.json";
+pub static PACK_VERSION: u32 = 1;
+// Folder inside a pack holding its download bundle
+pub static PACK_DOWNLOADS_DIR: &str = "downloads";
+
+// Keeps scratch folders apart when exports or imports start in the same second
+static NEXT_WORK_DIR: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackedMod {
+    pub id: String,
+    pub version: Option<String>,
+    // Path of the mudfile inside the pack
+    pub mudfile: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackSettings {
+    pub active: String,
+    pub profiles: BTreeMap<String, Map<String, Value>>,
+}
+
+/*
+A zip holding everything needed to reproduce a setup elsewhere:
+modpack.json       this manifest
+mudfiles/<id>.mud  mudfiles as of `repo_commit`, in install order
+downloads/         a download bundle, see downloads/bundle.rs
+*/
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModPack {
+    pub version: u32,
+    pub name: String,
+    pub game_id: String,
+    pub created_at: u64,
+    // Community repo commit the mudfiles were taken from
+    pub repo_commit: Option<String>,
+    pub mods: Vec<PackedMod>,
+    pub settings: Option<PackSettings>,
+    pub load_order: Option<LoadOrder>,
+}
+
+impl ModPack {
+    pub fn mudfile_path(mod_id: &str) -> String {
+        format!("mudfiles/{}.mud", mod_id)
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, AppError> {
+        let path = dir.join(PACK_MANIFEST);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| AppError::ParsingError(format!("Not a mod pack, {:?}: {}", path, e)))?;
+        let pack: Self =
+            serde_json::from_str(&content).map_err(|e| AppError::ParsingError(e.to_string()))?;
+        if pack.version > PACK_VERSION {
+            return Err(AppError::ParsingError(format!(
+                "Unsupported mod pack version {}",
+                pack.version
+            )));
+        }
+        Ok(pack)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), AppError> {
+        std::fs::create_dir_all(dir)?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::ParsingError(e.to_string()))?;
+        std::fs::write(dir.join(PACK_MANIFEST), content)?;
+        Ok(())
+    }
+
+    // Reads a packed mudfile, refusing paths that point outside the pack
+    pub fn read_mudfile(&self, dir: &Path, packed: &PackedMod) -> Result<String, AppError> {
+        let relative = Path::new(&packed.mudfile);
+        if relative.is_absolute() || relative.components().any(|c| c.as_os_str() == "..") {
+            return Err(AppError::ParsingError(format!(
+                "Invalid mudfile path '{}' in mod pack",
+                packed.mudfile
+            )));
+        }
+        Ok(std::fs::read_to_string(dir.join(relative))?)
+    }
+}
+
+// Scratch folder for building or unpacking a pack, under <appdata>/packs
+pub fn pack_work_dir(app_data: &Path, kind: &str) -> PathBuf {
+    app_data.join(PACKS_DIR).join(format!(
+        "{}-{}-{}-{}",
+        kind,
+        unix_now(),
+        std::process::id(),
+        NEXT_WORK_DIR.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+// Zips a pack folder into a single archive
+pub fn write_pack(dir: &Path, archive: &Path) -> Result<(), AppError> {
+    ModPack::load(dir)?;
+    Ok(create_zip(dir, archive)?)
+}
+
+// Unzips a pack archive into `dir`, returning its manifest
+pub fn unpack(archive: &Path, dir: &Path) -> Result<ModPack, AppError> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    extract_archive(archive, dir, ArchiveFormat::Zip, &ExtractOptions::default())?;
+    ModPack::load(dir)
+}
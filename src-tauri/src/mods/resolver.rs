@@ -0,0 +1,378 @@
+use crate::context::manifest::{ModDependency, ModManifest};
+use semver::VersionReq;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResolveError {
+    Missing {
+        dependency: ModDependency,
+        required_by: String,
+        available: Vec<String>,
+    },
+    VersionConflict {
+        dependency: ModDependency,
+        required_by: String,
+        selected: String,
+    },
+    Conflict {
+        first: String,
+        second: String,
+    },
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Missing {
+                dependency,
+                required_by,
+                available,
+            } if available.is_empty() => write!(
+                f,
+                "{} requires '{}' ({}), which is not in the catalog",
+                required_by, dependency.id, dependency.req
+            ),
+            ResolveError::Missing {
+                dependency,
+                required_by,
+                available,
+            } => write!(
+                f,
+                "{} requires '{}' ({}), but only {} available",
+                required_by,
+                dependency.id,
+                dependency.req,
+                available.join(", ")
+            ),
+            ResolveError::VersionConflict {
+                dependency,
+                required_by,
+                selected,
+            } => write!(
+                f,
+                "{} requires '{}' ({}), but {} is already selected",
+                required_by, dependency.id, dependency.req, selected
+            ),
+            ResolveError::Conflict { first, second } => {
+                write!(f, "{} conflicts with {}", first, second)
+            }
+            ResolveError::Cycle(ids) => {
+                write!(f, "dependency cycle between {}", ids.join(" -> "))
+            }
+        }
+    }
+}
+
+// Something that still needs a mod selected for it
+#[derive(Clone, Debug)]
+struct Requirement {
+    dependency: ModDependency,
+    required_by: String,
+}
+
+fn conflicts_with(a: &ModManifest, b: &ModManifest) -> bool {
+    let hits = |from: &ModManifest, to: &ModManifest| {
+        from.conflicts
+            .iter()
+            .any(|c| c.id == to.id && c.req.matches(&to.version_or_default()))
+    };
+    hits(a, b) || hits(b, a)
+}
+
+/*
+Picks one version of every requested mod and everything they depend on.
+Newest matching versions are tried first, backtracking on conflicts.
+*/
+pub fn resolve(
+    catalog: &[ModManifest],
+    requested: &[ModDependency],
+) -> Result<Vec<ModManifest>, ResolveError> {
+    // Every version of each mod, newest first
+    let mut versions: BTreeMap<&str, Vec<&ModManifest>> = BTreeMap::new();
+    for manifest in catalog {
+        versions.entry(&manifest.id).or_default().push(manifest);
+    }
+    for list in versions.values_mut() {
+        list.sort_by_key(|m| std::cmp::Reverse(m.version_or_default()));
+    }
+
+    let mut search = Search {
+        versions,
+        queue: requested
+            .iter()
+            .map(|dependency| Requirement {
+                dependency: dependency.clone(),
+                required_by: "request".to_string(),
+            })
+            .collect(),
+        selected: BTreeMap::new(),
+    };
+    search.select(0)?;
+    install_order(
+        search
+            .selected
+            .into_iter()
+            .map(|(id, m)| (id, m.clone()))
+            .collect(),
+    )
+}
+
+/*
+Backtracking state shared by every step. Choosing a candidate appends its
+dependencies to the queue and its manifest to `selected`, and undoing the
+choice truncates them again, so nothing is cloned per candidate.
+*/
+struct Search<'a> {
+    versions: BTreeMap<&'a str, Vec<&'a ModManifest>>,
+    queue: Vec<Requirement>,
+    selected: BTreeMap<String, &'a ModManifest>,
+}
+
+impl<'a> Search<'a> {
+    // Satisfies queue[next..], leaving the choices in `selected` on success and nothing on failure
+    fn select(&mut self, mut next: usize) -> Result<(), ResolveError> {
+        // Requirements an earlier choice already covers need no new choice
+        let requirement = loop {
+            let requirement = match self.queue.get(next) {
+                Some(requirement) => requirement.clone(),
+                None => return Ok(()),
+            };
+            let dependency = &requirement.dependency;
+            match self.selected.get(&dependency.id) {
+                Some(existing) if dependency.req.matches(&existing.version_or_default()) => {
+                    next += 1
+                }
+                Some(existing) => {
+                    return Err(ResolveError::VersionConflict {
+                        dependency: dependency.clone(),
+                        required_by: requirement.required_by,
+                        selected: existing.display_name(),
+                    })
+                }
+                None => break requirement,
+            }
+        };
+        let dependency = &requirement.dependency;
+
+        let versions: &[&'a ModManifest] = self
+            .versions
+            .get(dependency.id.as_str())
+            .map(|v| v.as_slice())
+            .unwrap_or_default();
+        let candidates: Vec<&'a ModManifest> = versions
+            .iter()
+            .copied()
+            .filter(|m| dependency.req.matches(&m.version_or_default()))
+            .collect();
+        if candidates.is_empty() {
+            return Err(ResolveError::Missing {
+                dependency: dependency.clone(),
+                required_by: requirement.required_by,
+                available: versions.iter().map(|m| m.display_name()).collect(),
+            });
+        }
+
+        let mut last_error = None;
+        for candidate in candidates {
+            if let Some(other) = self
+                .selected
+                .values()
+                .find(|s| conflicts_with(candidate, s))
+            {
+                last_error = Some(ResolveError::Conflict {
+                    first: candidate.display_name(),
+                    second: other.display_name(),
+                });
+                continue;
+            }
+
+            let queued = self.queue.len();
+            self.queue
+                .extend(candidate.dependencies.iter().map(|d| Requirement {
+                    dependency: d.clone(),
+                    required_by: candidate.display_name(),
+                }));
+            self.selected.insert(candidate.id.clone(), candidate);
+
+            match self.select(next + 1) {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+            // A failed step leaves the state as it found it, so only this choice needs undoing
+            self.queue.truncate(queued);
+            self.selected.remove(&candidate.id);
+        }
+        Err(last_error.unwrap_or(ResolveError::Missing {
+            dependency: dependency.clone(),
+            required_by: requirement.required_by,
+            available: Vec::new(),
+        }))
+    }
+}
+
+// Orders selected mods so every dependency comes before the mods needing it
+pub fn install_order(
+    selected: BTreeMap<String, ModManifest>,
+) -> Result<Vec<ModManifest>, ResolveError> {
+    let mut remaining: BTreeMap<String, BTreeSet<String>> = selected
+        .values()
+        .map(|m| {
+            let deps = m
+                .dependencies
+                .iter()
+                .map(|d| d.id.clone())
+                .filter(|id| selected.contains_key(id))
+                .collect();
+            (m.id.clone(), deps)
+        })
+        .collect();
+
+    let mut order = Vec::new();
+    while !remaining.is_empty() {
+        let ready: Vec<String> = remaining
+            .iter()
+            .filter(|(_, deps)| deps.is_empty())
+            .map(|(id, _)| id.clone())
+            .collect();
+        if ready.is_empty() {
+            return Err(ResolveError::Cycle(remaining.keys().cloned().collect()));
+        }
+        for id in ready {
+            remaining.remove(&id);
+            for deps in remaining.values_mut() {
+                deps.remove(&id);
+            }
+            order.push(selected[&id].clone());
+        }
+    }
+    Ok(order)
+}
+
+// Parses `<id>` or `<id>@<requirement>` as typed by users
+pub fn parse_request(request: &str) -> Result<ModDependency, String> {
+    match request.split_once('@') {
+        Some((id, req)) => ModDependency::parse(&format!("{} {}", id, req)),
+        None => Ok(ModDependency {
+            id: request.trim().to_string(),
+            req: VersionReq::STAR,
+        }),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use semver::Version;
+
+    pub fn manifest(
+        id: &str,
+        version: &str,
+        dependencies: &[&str],
+        conflicts: &[&str],
+    ) -> ModManifest {
+        let parse = |values: &[&str]| {
+            values
+                .iter()
+                .map(|v| ModDependency::parse(v).unwrap())
+                .collect()
+        };
+        ModManifest {
+            id: id.to_string(),
+            version: Some(Version::parse(version).unwrap()),
+            dependencies: parse(dependencies),
+            conflicts: parse(conflicts),
+            ..Default::default()
+        }
+    }
+
+    fn request(values: &[&str]) -> Vec<ModDependency> {
+        values
+            .iter()
+            .map(|v| ModDependency::parse(v).unwrap())
+            .collect()
+    }
+
+    fn resolve_error(catalog: &[ModManifest], requested: &[&str]) -> String {
+        resolve(catalog, &request(requested))
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn orders_dependencies_first() {
+        let catalog = vec![
+            manifest("ui", "1.0.0", &["core ^1"], &[]),
+            manifest("core", "1.2.0", &[], &[]),
+        ];
+        let resolved = resolve(&catalog, &request(&["ui"])).unwrap();
+        let ids: Vec<&str> = resolved.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["core", "ui"]);
+    }
+
+    #[test]
+    fn backtracks_to_an_older_version() {
+        let catalog = vec![
+            manifest("ui", "2.0.0", &["core ^2"], &[]),
+            manifest("ui", "1.0.0", &["core ^1"], &[]),
+            manifest("core", "1.0.0", &[], &[]),
+        ];
+        let resolved = resolve(&catalog, &request(&["ui"])).unwrap();
+        let names: Vec<String> = resolved.iter().map(|m| m.display_name()).collect();
+        assert_eq!(names, ["core@1.0.0", "ui@1.0.0"]);
+    }
+
+    #[test]
+    fn reports_a_mod_missing_from_the_catalog() {
+        assert_eq!(
+            resolve_error(&[], &["ghost"]),
+            "request requires 'ghost' (*), which is not in the catalog"
+        );
+    }
+
+    #[test]
+    fn reports_versions_that_dont_match() {
+        let catalog = vec![manifest("core", "1.0.0", &[], &[])];
+        assert_eq!(
+            resolve_error(&catalog, &["core ^2"]),
+            "request requires 'core' (^2), but only core@1.0.0 available"
+        );
+    }
+
+    #[test]
+    fn reports_a_version_conflict() {
+        let catalog = vec![
+            manifest("core", "1.0.0", &[], &[]),
+            manifest("ui", "1.0.0", &["core ^2"], &[]),
+        ];
+        assert_eq!(
+            resolve_error(&catalog, &["core =1.0.0", "ui"]),
+            "ui@1.0.0 requires 'core' (^2), but core@1.0.0 is already selected"
+        );
+    }
+
+    #[test]
+    fn reports_a_conflict() {
+        let catalog = vec![
+            manifest("lighting", "1.0.0", &[], &["shaders"]),
+            manifest("shaders", "1.0.0", &[], &[]),
+        ];
+        assert_eq!(
+            resolve_error(&catalog, &["lighting", "shaders"]),
+            "shaders@1.0.0 conflicts with lighting@1.0.0"
+        );
+    }
+
+    #[test]
+    fn reports_a_cycle() {
+        let catalog = vec![
+            manifest("a", "1.0.0", &["b"], &[]),
+            manifest("b", "1.0.0", &["a"], &[]),
+        ];
+        assert_eq!(
+            resolve_error(&catalog, &["a"]),
+            "dependency cycle between a -> b"
+        );
+    }
+}
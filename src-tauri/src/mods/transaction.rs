@@ -0,0 +1,175 @@
+use crate::mods::ledger::{unix_now, SharedSession};
+use crate::utils::error_handler::AppError;
+use crate::utils::files::{copy_path, remove_path, walk_files};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub static TRANSACTIONS_DIR: &str = "transactions";
+
+// Keeps shadow directories apart when runs start in the same second
+static NEXT_TRANSACTION: AtomicUsize = AtomicUsize::new(0);
+
+/*
+Filesystem writes from a transactional run land in a shadow directory that
+mirrors absolute paths. Nothing touches the real paths until `commit`.
+*/
+pub struct Transaction {
+    shadow: PathBuf,
+    staged: BTreeSet<PathBuf>,
+    removed: BTreeSet<PathBuf>,
+}
+
+pub type SharedTransaction = Arc<Mutex<Transaction>>;
+
+impl Transaction {
+    pub fn new(app_data: &Path) -> Self {
+        Self {
+            shadow: app_data.join(TRANSACTIONS_DIR).join(format!(
+                "{}-{}-{}",
+                unix_now(),
+                std::process::id(),
+                NEXT_TRANSACTION.fetch_add(1, Ordering::Relaxed)
+            )),
+            staged: BTreeSet::new(),
+            removed: BTreeSet::new(),
+        }
+    }
+
+    pub fn shared(self) -> SharedTransaction {
+        Arc::new(Mutex::new(self))
+    }
+
+    // Where a real (absolute) path lives inside the shadow directory
+    pub fn shadow_path(&self, real: &Path) -> PathBuf {
+        let mut path = self.shadow.clone();
+        for component in real.components() {
+            match component {
+                Component::Prefix(prefix) => {
+                    path.push(prefix.as_os_str().to_string_lossy().replace(':', ""))
+                }
+                Component::Normal(part) => path.push(part),
+                _ => {}
+            }
+        }
+        path
+    }
+
+    fn is_staged(&self, real: &Path) -> bool {
+        self.staged.iter().any(|s| real.starts_with(s))
+    }
+
+    fn is_removed(&self, real: &Path) -> bool {
+        self.removed.iter().any(|r| real.starts_with(r))
+    }
+
+    // Path a read should use: the staged copy if there is one
+    pub fn resolve_read(&self, real: &Path) -> PathBuf {
+        if self.is_staged(real) || self.is_removed(real) {
+            self.shadow_path(real)
+        } else {
+            real.to_path_buf()
+        }
+    }
+
+    // Prepares a shadow copy of a path for writing, copying existing content first
+    pub fn stage_write(&mut self, real: &Path) -> io::Result<PathBuf> {
+        let shadow = self.shadow_path(real);
+        if !self.is_staged(real) {
+            if real.exists() && !self.is_removed(real) {
+                copy_path(real, &shadow)?;
+                // copy_path only copies files, empty folders need creating
+                if real.is_dir() {
+                    std::fs::create_dir_all(&shadow)?;
+                }
+            }
+            self.staged.insert(real.to_path_buf());
+        }
+        if let Some(parent) = shadow.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(shadow)
+    }
+
+    // Marks a path for removal at commit, dropping anything staged beneath it
+    pub fn stage_remove(&mut self, real: &Path) -> io::Result<()> {
+        self.staged.retain(|s| !s.starts_with(real));
+        remove_path(&self.shadow_path(real))?;
+        self.removed.insert(real.to_path_buf());
+        Ok(())
+    }
+
+    // Every real path the commit will remove or write
+    pub fn targets(&self) -> Vec<PathBuf> {
+        let mut targets: Vec<PathBuf> = self.removed.iter().cloned().collect();
+        for staged in &self.staged {
+            let shadow = self.shadow_path(staged);
+            if shadow.is_dir() {
+                for file in walk_files(&shadow) {
+                    if let Ok(relative) = file.strip_prefix(&shadow) {
+                        targets.push(staged.join(relative));
+                    }
+                }
+            } else {
+                targets.push(staged.clone());
+            }
+        }
+        targets
+    }
+
+    // Applies removals, then copies staged content over the real paths
+    pub fn commit(&self) -> io::Result<Vec<PathBuf>> {
+        for removed in &self.removed {
+            remove_path(removed)?;
+        }
+        for staged in &self.staged {
+            let shadow = self.shadow_path(staged);
+            if shadow.is_dir() {
+                std::fs::create_dir_all(staged)?;
+            }
+            if shadow.exists() {
+                copy_path(&shadow, staged)?;
+            }
+        }
+        let targets = self.targets();
+        self.discard()?;
+        Ok(targets)
+    }
+
+    pub fn discard(&self) -> io::Result<()> {
+        remove_path(&self.shadow)
+    }
+}
+
+/*
+Commits a transaction, letting the install session back up every real path first
+so the result still shows up in the ledger and can be uninstalled.
+*/
+pub fn commit_transaction(
+    transaction: &SharedTransaction,
+    session: Option<&SharedSession>,
+) -> Result<Vec<PathBuf>, AppError> {
+    let transaction = transaction
+        .lock()
+        .map_err(|e| AppError::InstallError(e.to_string()))?;
+
+    if let Some(session) = session {
+        let mut session = session
+            .lock()
+            .map_err(|e| AppError::InstallError(e.to_string()))?;
+        for target in transaction.targets() {
+            session.track(&target)?;
+        }
+    }
+    Ok(transaction.commit()?)
+}
+
+pub fn discard_transaction(transaction: &SharedTransaction) -> Result<(), AppError> {
+    transaction
+        .lock()
+        .map_err(|e| AppError::InstallError(e.to_string()))?
+        .discard()?;
+    Ok(())
+}
@@ -0,0 +1,371 @@
+use crate::mods::ledger::{
+    backup_dir, backup_name, file_action, restore_file, run_backup_dir, FileAction, FileRecord,
+    InstallRecord, Ledger, LEDGER_DIR,
+};
+use crate::utils::error_handler::AppError;
+use crate::utils::hashing::hash_file;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifiedFile {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+// What an uninstall or rollback did, or would have done when `applied` is false
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub applied: bool,
+    pub removed: Vec<PathBuf>,
+    pub restored: Vec<PathBuf>,
+    pub modified: Vec<ModifiedFile>,
+}
+
+// Files changed since the record was written, either by the user or by a mod installed over it
+pub fn find_modified(
+    ledger: &Ledger,
+    record: &InstallRecord,
+    files: &[FileRecord],
+) -> Vec<ModifiedFile> {
+    let mut modified = Vec::new();
+    for file in files {
+        let current = if file.path.is_file() {
+            hash_file(&file.path).ok()
+        } else {
+            None
+        };
+
+        let reason = if let Some(other) = ledger.above(record, file) {
+            format!("also written by mod '{}'", other.mod_id)
+        } else if current != file.hash {
+            "changed on disk since it was installed".to_string()
+        } else {
+            continue;
+        };
+        modified.push(ModifiedFile {
+            path: file.path.clone(),
+            reason,
+        });
+    }
+    modified
+}
+
+fn apply_restore(files: &[FileRecord], report: &mut RestoreReport) -> Result<(), AppError> {
+    for file in files {
+        restore_file(&file.path, &file.backup)?;
+        if file.action == FileAction::Created {
+            report.removed.push(file.path.clone());
+        } else {
+            report.restored.push(file.path.clone());
+        }
+    }
+    report.applied = true;
+    Ok(())
+}
+
+fn find_record(ledger: &Ledger, game_id: &str, mod_id: &str) -> Result<InstallRecord, AppError> {
+    ledger.find(game_id, mod_id).cloned().ok_or_else(|| {
+        AppError::InstallError(format!(
+            "Mod '{}' is not installed for '{}'",
+            mod_id, game_id
+        ))
+    })
+}
+
+fn remove_mod_data(app_data: &Path, game_id: &str, mod_id: &str) -> Result<(), AppError> {
+    let mod_dir = app_data.join(LEDGER_DIR).join(game_id).join(mod_id);
+    if mod_dir.exists() {
+        std::fs::remove_dir_all(mod_dir)?;
+    }
+    Ok(())
+}
+
+// What uninstall would refuse over, without changing anything
+pub fn check_uninstall(
+    app_data: &Path,
+    game_id: &str,
+    mod_id: &str,
+) -> Result<RestoreReport, AppError> {
+    let ledger = Ledger::load(app_data)?;
+    let record = find_record(&ledger, game_id, mod_id)?;
+    Ok(RestoreReport {
+        modified: find_modified(&ledger, &record, &record.files),
+        ..Default::default()
+    })
+}
+
+/*
+Removes every file a mod added and restores every file it overwrote or deleted.
+Refuses when files were modified afterwards unless `force` is set.
+*/
+pub fn uninstall(
+    app_data: &Path,
+    game_id: &str,
+    mod_id: &str,
+    force: bool,
+) -> Result<RestoreReport, AppError> {
+    let mut ledger = Ledger::load(app_data)?;
+    let record = find_record(&ledger, game_id, mod_id)?;
+
+    let mut report = RestoreReport {
+        modified: find_modified(&ledger, &record, &record.files),
+        ..Default::default()
+    };
+    if !report.modified.is_empty() && !force {
+        return Ok(report);
+    }
+
+    apply_restore(&record.files, &mut report)?;
+    ledger.remove(game_id, mod_id);
+    ledger.save(app_data)?;
+    remove_mod_data(app_data, game_id, mod_id)?;
+    Ok(report)
+}
+
+// Undoes only the most recent install run of a mod, returning it to its previous version
+pub fn rollback(
+    app_data: &Path,
+    game_id: &str,
+    mod_id: &str,
+    force: bool,
+) -> Result<RestoreReport, AppError> {
+    let mut ledger = Ledger::load(app_data)?;
+    let mut record = find_record(&ledger, game_id, mod_id)?;
+
+    let mut report = RestoreReport {
+        modified: find_modified(&ledger, &record, &record.last_run),
+        ..Default::default()
+    };
+    if !report.modified.is_empty() && !force {
+        return Ok(report);
+    }
+
+    apply_restore(&record.last_run, &mut report)?;
+    ledger.remove(game_id, mod_id);
+
+    match record.previous_installed_at {
+        // First install, nothing left to track
+        None => remove_mod_data(app_data, game_id, mod_id)?,
+        Some(previous_installed_at) => {
+            let run_dir = run_backup_dir(app_data, game_id, mod_id, record.installed_at);
+            let last_run = std::mem::take(&mut record.last_run);
+            // Files the run left in their original state were dropped from the record
+            let dropped: Vec<&FileRecord> = last_run
+                .iter()
+                .filter(|run| record.file(&run.path).is_none())
+                .collect();
+
+            record.files.retain_mut(|file| {
+                if let Some(run) = last_run.iter().find(|f| f.path == file.path) {
+                    file.hash = run.original_hash.clone();
+                    match file_action(&file.hash, &file.original_hash) {
+                        Some(action) => file.action = action,
+                        None => return false,
+                    }
+                }
+                true
+            });
+            for run in dropped {
+                let original_hash = run.hash.clone();
+                if let Some(action) = file_action(&run.original_hash, &original_hash) {
+                    record.files.push(FileRecord {
+                        path: run.path.clone(),
+                        action,
+                        hash: run.original_hash.clone(),
+                        backup: original_hash.as_ref().map(|_| {
+                            backup_dir(app_data, game_id, mod_id).join(backup_name(&run.path))
+                        }),
+                        original_hash,
+                    });
+                }
+            }
+            record.version = record.previous_version.take();
+            record.installed_at = previous_installed_at;
+            record.previous_installed_at = None;
+            ledger.installs.push(record);
+
+            if run_dir.exists() {
+                std::fs::remove_dir_all(run_dir)?;
+            }
+        }
+    }
+
+    ledger.save(app_data)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mods::ledger::tests::{install, read, write, GAME};
+    use crate::utils::hashing::hash_bytes;
+    use crate::utils::testing::temp_dir;
+
+    fn reasons(report: &RestoreReport) -> Vec<(PathBuf, String)> {
+        report
+            .modified
+            .iter()
+            .map(|m| (m.path.clone(), m.reason.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn refuses_files_changed_on_disk_without_force() {
+        let root = temp_dir("uninstall-changed");
+        let (app_data, data) = (root.join("appdata"), root.join("game/data.pak"));
+        write(&data, "vanilla");
+        install(&app_data, "mod", "1.0.0", &[(&data, Some("modded"))]);
+        write(&data, "edited by hand");
+
+        let check = check_uninstall(&app_data, GAME, "mod").unwrap();
+        let report = uninstall(&app_data, GAME, "mod", false).unwrap();
+        assert!(!report.applied);
+        assert_eq!(reasons(&check), reasons(&report));
+        assert_eq!(
+            reasons(&report),
+            vec![(
+                data.clone(),
+                "changed on disk since it was installed".to_string()
+            )]
+        );
+        assert_eq!(read(&data).as_deref(), Some("edited by hand"));
+        assert!(Ledger::load(&app_data).unwrap().find(GAME, "mod").is_some());
+
+        let report = uninstall(&app_data, GAME, "mod", true).unwrap();
+        assert!(report.applied);
+        assert_eq!(report.restored, vec![data.clone()]);
+        assert_eq!(read(&data).as_deref(), Some("vanilla"));
+        assert!(Ledger::load(&app_data).unwrap().installs.is_empty());
+        assert!(!app_data.join(LEDGER_DIR).join(GAME).join("mod").exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn refuses_files_a_later_mod_wrote() {
+        let root = temp_dir("uninstall-later");
+        let (app_data, data) = (root.join("appdata"), root.join("game/data.pak"));
+        let created = root.join("game/Mods/first.pak");
+        write(&data, "vanilla");
+        install(
+            &app_data,
+            "first",
+            "1.0.0",
+            &[(&data, Some("first")), (&created, Some("first"))],
+        );
+        install(&app_data, "second", "1.0.0", &[(&data, Some("second"))]);
+
+        let report = uninstall(&app_data, GAME, "first", false).unwrap();
+        assert!(!report.applied);
+        assert_eq!(
+            reasons(&report),
+            vec![(data.clone(), "also written by mod 'second'".to_string())]
+        );
+        assert!(created.exists());
+
+        // The later mod comes off cleanly and hands the file back to the first
+        let report = uninstall(&app_data, GAME, "second", false).unwrap();
+        assert!(report.applied);
+        assert_eq!(read(&data).as_deref(), Some("first"));
+
+        let report = uninstall(&app_data, GAME, "first", false).unwrap();
+        assert!(report.applied);
+        assert_eq!(report.removed, vec![created.clone()]);
+        assert_eq!(read(&data).as_deref(), Some("vanilla"));
+        assert!(!created.exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn rollback_returns_to_the_previous_version() {
+        let root = temp_dir("uninstall-rollback");
+        let app_data = root.join("appdata");
+        let game = root.join("game");
+        let (data, config, shaders, kept, added) = (
+            game.join("data.pak"),
+            game.join("config.ini"),
+            game.join("shaders.pak"),
+            game.join("Mods/kept.pak"),
+            game.join("Mods/added.pak"),
+        );
+        for path in [&data, &config, &shaders] {
+            write(path, "vanilla");
+        }
+
+        install(
+            &app_data,
+            "mod",
+            "1.0.0",
+            &[
+                (&data, Some("v1")),
+                (&shaders, Some("v1")),
+                (&kept, Some("kept")),
+            ],
+        );
+        install(
+            &app_data,
+            "mod",
+            "2.0.0",
+            &[
+                (&data, Some("v2")),
+                (&config, Some("v2")),
+                // Undone by 2.0.0, which drops them from the record
+                (&shaders, Some("vanilla")),
+                (&kept, None),
+                (&added, Some("added")),
+            ],
+        );
+
+        let report = rollback(&app_data, GAME, "mod", false).unwrap();
+        assert!(report.applied);
+        assert_eq!(report.removed, vec![added.clone()]);
+        assert_eq!(read(&data).as_deref(), Some("v1"));
+        assert_eq!(read(&config).as_deref(), Some("vanilla"));
+        assert_eq!(read(&shaders).as_deref(), Some("v1"));
+        assert_eq!(read(&kept).as_deref(), Some("kept"));
+        assert!(!added.exists());
+
+        let ledger = Ledger::load(&app_data).unwrap();
+        let record = ledger.find(GAME, "mod").unwrap();
+        assert_eq!(record.version.as_deref(), Some("1.0.0"));
+        assert_eq!(record.previous_version, None);
+        assert!(record.last_run.is_empty());
+        // Back to what 1.0.0 left, config is vanilla again and added never existed
+        let mut files: Vec<_> = record
+            .files
+            .iter()
+            .map(|f| (f.path.clone(), f.action.clone(), f.hash.clone()))
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            files,
+            vec![
+                (kept.clone(), FileAction::Created, Some(hash_bytes(b"kept"))),
+                (
+                    data.clone(),
+                    FileAction::Overwritten,
+                    Some(hash_bytes(b"v1"))
+                ),
+                (
+                    shaders.clone(),
+                    FileAction::Overwritten,
+                    Some(hash_bytes(b"v1"))
+                ),
+            ]
+        );
+
+        // Which still uninstalls back to vanilla
+        let report = uninstall(&app_data, GAME, "mod", false).unwrap();
+        assert!(report.applied);
+        for path in [&data, &config, &shaders] {
+            assert_eq!(read(path).as_deref(), Some("vanilla"));
+        }
+        assert!(!kept.exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
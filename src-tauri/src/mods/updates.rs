@@ -0,0 +1,244 @@
+use crate::context::manifest::{ModDependency, ModManifest};
+use crate::mods::resolver::{resolve, ResolveError};
+use semver::{Comparator, Op, Version, VersionReq};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModUpdate {
+    pub mod_id: String,
+    pub installed: Option<String>,
+    // Newest version in the catalog
+    pub latest: String,
+    // Newest version the other installed mods allow, None when they allow no newer one
+    pub compatible: Option<String>,
+    // Installed mods whose requirements rule out `latest`
+    pub held_back_by: Vec<String>,
+}
+
+fn comparator(op: Op, version: &Version) -> VersionReq {
+    VersionReq {
+        comparators: vec![Comparator {
+            op,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: Some(version.patch),
+            pre: version.pre.clone(),
+        }],
+    }
+}
+
+/*
+Catalog manifest matching an installed version. A synced repo usually only
+keeps the newest mudfile of a mod, so when the installed one is gone (or its
+version unknown) the newest catalog version stands in for it.
+*/
+fn installed_manifest<'a>(
+    catalog: &'a [ModManifest],
+    mod_id: &str,
+    version: Option<&Version>,
+) -> Option<&'a ModManifest> {
+    let versions = || catalog.iter().filter(|m| m.id == mod_id);
+    version
+        .and_then(|version| versions().find(|m| m.version.as_ref() == Some(version)))
+        .or_else(|| versions().max_by_key(|m| m.version_or_default()))
+}
+
+/*
+Installed mods with a newer version in the catalog. `installed` maps mod ids
+to their installed versions, mods missing from the catalog are ignored.
+*/
+pub fn find_updates(
+    catalog: &[ModManifest],
+    installed: &BTreeMap<String, Option<Version>>,
+) -> Vec<ModUpdate> {
+    let mut updates = Vec::new();
+    for (mod_id, version) in installed {
+        let current = version.clone().unwrap_or(Version::new(0, 0, 0));
+        let mut newer: Vec<&ModManifest> = catalog
+            .iter()
+            .filter(|m| &m.id == mod_id && m.version_or_default() > current)
+            .collect();
+        if newer.is_empty() {
+            continue;
+        }
+        newer.sort_by_key(|m| std::cmp::Reverse(m.version_or_default()));
+
+        // Requirements other installed mods place on this one
+        let requirements: Vec<(String, &ModDependency)> = installed
+            .iter()
+            .filter(|(other, _)| *other != mod_id)
+            .filter_map(|(other, v)| installed_manifest(catalog, other, v.as_ref()))
+            .flat_map(|m| {
+                m.dependencies
+                    .iter()
+                    .filter(|d| &d.id == mod_id)
+                    .map(|d| (m.display_name(), d))
+            })
+            .collect();
+        let allows = |m: &ModManifest| {
+            requirements
+                .iter()
+                .all(|(_, d)| d.req.matches(&m.version_or_default()))
+        };
+
+        let latest = newer[0];
+        updates.push(ModUpdate {
+            mod_id: mod_id.clone(),
+            installed: version.as_ref().map(|v| v.to_string()),
+            latest: latest.version_or_default().to_string(),
+            compatible: newer
+                .iter()
+                .find(|m| allows(m))
+                .map(|m| m.version_or_default().to_string()),
+            held_back_by: requirements
+                .iter()
+                .filter(|(_, d)| !d.req.matches(&latest.version_or_default()))
+                .map(|(name, _)| name.clone())
+                .collect(),
+        });
+    }
+    updates
+}
+
+/*
+Resolves the installed set again with `targets` free to move to their newest
+allowed versions and every other mod pinned to what's installed. A pinned mod
+whose version left the catalog may match any version from the installed one
+up, it stays as it is either way. Nothing is downgraded. Returns the mods that
+need installing, dependencies first: upgraded targets plus any new
+dependencies they pull in.
+*/
+pub fn plan_upgrade(
+    catalog: &[ModManifest],
+    installed: &BTreeMap<String, Option<Version>>,
+    targets: &[String],
+) -> Result<Vec<ModManifest>, ResolveError> {
+    let requested: Vec<ModDependency> = installed
+        .iter()
+        .filter(|(mod_id, _)| catalog.iter().any(|m| &m.id == *mod_id))
+        .map(|(mod_id, version)| {
+            let in_catalog = |version: &Version| {
+                catalog
+                    .iter()
+                    .any(|m| &m.id == mod_id && m.version.as_ref() == Some(version))
+            };
+            let req = match version {
+                None => VersionReq::STAR,
+                Some(version) if !targets.contains(mod_id) && in_catalog(version) => {
+                    comparator(Op::Exact, version)
+                }
+                Some(version) => comparator(Op::GreaterEq, version),
+            };
+            ModDependency {
+                id: mod_id.clone(),
+                req,
+            }
+        })
+        .collect();
+
+    Ok(resolve(catalog, &requested)?
+        .into_iter()
+        .filter(|m| match installed.get(&m.id) {
+            Some(version) => targets.contains(&m.id) && version.as_ref() != m.version.as_ref(),
+            None => true,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mods::resolver::tests::manifest;
+
+    fn installed(values: &[(&str, &str)]) -> BTreeMap<String, Option<Version>> {
+        values
+            .iter()
+            .map(|(id, version)| (id.to_string(), Some(Version::parse(version).unwrap())))
+            .collect()
+    }
+
+    fn names(plan: &[ModManifest]) -> Vec<String> {
+        plan.iter().map(|m| m.display_name()).collect()
+    }
+
+    fn targets(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn upgrades_when_other_installed_versions_left_the_catalog() {
+        // Only the newest mudfile of each mod is left after a sync
+        let catalog = vec![
+            manifest("core", "1.2.0", &[], &[]),
+            manifest("ui", "1.1.0", &["core ^1.1"], &[]),
+            manifest("maps", "2.0.0", &["core ^1"], &[]),
+        ];
+        let installed = installed(&[("core", "1.0.0"), ("ui", "1.0.0"), ("maps", "1.0.0")]);
+
+        let plan = plan_upgrade(&catalog, &installed, &targets(&["ui"])).unwrap();
+        assert_eq!(names(&plan), ["ui@1.1.0"]);
+
+        let everything = targets(&["core", "ui", "maps"]);
+        let plan = plan_upgrade(&catalog, &installed, &everything).unwrap();
+        assert_eq!(names(&plan), ["core@1.2.0", "maps@2.0.0", "ui@1.1.0"]);
+    }
+
+    #[test]
+    fn keeps_pinned_mods_that_are_still_in_the_catalog() {
+        let catalog = vec![
+            manifest("core", "2.0.0", &[], &[]),
+            manifest("core", "1.0.0", &[], &[]),
+            manifest("ui", "1.1.0", &["core ^1"], &[]),
+            manifest("ui", "1.0.0", &["core ^1"], &[]),
+        ];
+        let installed = installed(&[("core", "1.0.0"), ("ui", "1.0.0")]);
+        let plan = plan_upgrade(&catalog, &installed, &targets(&["ui"])).unwrap();
+        assert_eq!(names(&plan), ["ui@1.1.0"]);
+    }
+
+    #[test]
+    fn never_downgrades_a_target() {
+        let catalog = vec![
+            manifest("ui", "2.0.0", &["core ^2"], &[]),
+            manifest("ui", "1.0.0", &["core ^1"], &[]),
+            manifest("core", "1.0.0", &[], &[]),
+        ];
+        let installed = installed(&[("core", "1.0.0"), ("ui", "2.0.0")]);
+        // Resolving from scratch would settle on ui 1.0.0
+        assert!(plan_upgrade(&catalog, &installed, &targets(&["ui"])).is_err());
+
+        let catalog = vec![manifest("ui", "1.0.0", &[], &[])];
+        let installed = BTreeMap::from([("ui".to_string(), Some(Version::new(2, 0, 0)))]);
+        assert_eq!(
+            plan_upgrade(&catalog, &installed, &targets(&["ui"])).unwrap_err(),
+            ResolveError::Missing {
+                dependency: ModDependency::parse("ui >=2.0.0").unwrap(),
+                required_by: "request".to_string(),
+                available: vec!["ui@1.0.0".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn finds_requirements_of_mods_whose_version_left_the_catalog() {
+        let catalog = vec![
+            manifest("core", "2.0.0", &[], &[]),
+            manifest("core", "1.5.0", &[], &[]),
+            manifest("ui", "1.1.0", &["core ^1"], &[]),
+        ];
+        let installed = installed(&[("core", "1.0.0"), ("ui", "1.0.0")]);
+        let updates = find_updates(&catalog, &installed);
+
+        assert_eq!(updates.len(), 2);
+        let core = &updates[0];
+        assert_eq!(core.mod_id, "core");
+        assert_eq!(core.latest, "2.0.0");
+        assert_eq!(core.compatible.as_deref(), Some("1.5.0"));
+        assert_eq!(core.held_back_by, ["ui@1.1.0"]);
+        let ui = &updates[1];
+        assert_eq!(ui.compatible.as_deref(), Some("1.1.0"));
+        assert!(ui.held_back_by.is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::context::manifest::ModManifest;
+use crate::mods::loadorder::LoadOrderEntry;
+use std::io;
+use std::path::Path;
+
+// An entry in the order, along with its manifest when the mod is in the catalog
+pub struct WriterEntry<'a> {
+    pub entry: &'a LoadOrderEntry,
+    pub manifest: Option<&'a ModManifest>,
+}
+
+impl WriterEntry<'_> {
+    // Game specific manifest value, e.g. `# mud:plugin MyMod.esp`
+    pub fn extra(&self, key: &str) -> Option<&str> {
+        self.manifest
+            .and_then(|m| m.extra.get(key))
+            .map(|v| v.as_str())
+    }
+
+    pub fn name(&self) -> &str {
+        self.manifest
+            .and_then(|m| m.name.as_deref())
+            .unwrap_or(&self.entry.mod_id)
+    }
+}
+
+/*
+Emits the file a game reads its load order from. A game's config.json picks
+one with `"loadOrder": { "format": "<format>", "path": "<file>" }`.
+*/
+pub trait LoadOrderWriter {
+    fn format(&self) -> &'static str;
+    fn render(&self, entries: &[WriterEntry]) -> String;
+}
+
+// Bethesda style plugins.txt, enabled plugins are prefixed with `*`
+pub struct PluginsTxtWriter;
+
+impl LoadOrderWriter for PluginsTxtWriter {
+    fn format(&self) -> &'static str {
+        "plugins.txt"
+    }
+
+    fn render(&self, entries: &[WriterEntry]) -> String {
+        let mut out = String::from("# This file is generated by Mud\n");
+        for entry in entries {
+            let plugin = entry.extra("plugin").unwrap_or(&entry.entry.mod_id);
+            let marker = if entry.entry.enabled { "*" } else { "" };
+            out.push_str(&format!("{}{}\n", marker, plugin));
+        }
+        out
+    }
+}
+
+// One enabled mod id per line, for games that just read a list
+pub struct PlainListWriter;
+
+impl LoadOrderWriter for PlainListWriter {
+    fn format(&self) -> &'static str {
+        "list"
+    }
+
+    fn render(&self, entries: &[WriterEntry]) -> String {
+        entries
+            .iter()
+            .filter(|e| e.entry.enabled)
+            .map(|e| format!("{}\n", e.extra("file").unwrap_or(&e.entry.mod_id)))
+            .collect()
+    }
+}
+
+// Baldur's Gate 3 modsettings.lsx, mods need `uuid` (and optionally `folder`) in their manifest
+pub struct ModSettingsLsxWriter;
+
+static BG3_BASE_MODULE: (&str, &str) = ("GustavDev", "28ac9ce2-2aba-8cda-b3b5-6e922f71b6b8");
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn lsx_module(folder: &str, name: &str, uuid: &str) -> String {
+    format!(
+        "            <node id=\"ModuleShortDesc\">\n\
+        \x20             <attribute id=\"Folder\" type=\"LSString\" value=\"{}\"/>\n\
+        \x20             <attribute id=\"MD5\" type=\"LSString\" value=\"\"/>\n\
+        \x20             <attribute id=\"Name\" type=\"LSString\" value=\"{}\"/>\n\
+        \x20             <attribute id=\"UUID\" type=\"guid\" value=\"{}\"/>\n\
+        \x20             <attribute id=\"Version64\" type=\"int64\" value=\"36028797018963968\"/>\n\
+        \x20           </node>\n",
+        xml_escape(folder),
+        xml_escape(name),
+        xml_escape(uuid)
+    )
+}
+
+impl LoadOrderWriter for ModSettingsLsxWriter {
+    fn format(&self) -> &'static str {
+        "modsettings.lsx"
+    }
+
+    fn render(&self, entries: &[WriterEntry]) -> String {
+        let (base_name, base_uuid) = BG3_BASE_MODULE;
+        let mut modules = lsx_module(base_name, base_name, base_uuid);
+        for entry in entries.iter().filter(|e| e.entry.enabled) {
+            let uuid = match entry.extra("uuid") {
+                Some(uuid) => uuid,
+                None => continue, // Not a pak mod, nothing for the game to load
+            };
+            let folder = entry.extra("folder").unwrap_or(&entry.entry.mod_id);
+            modules.push_str(&lsx_module(folder, entry.name(), uuid));
+        }
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <save>\n\
+            \x20 <version major=\"4\" minor=\"7\" revision=\"1\" build=\"3\"/>\n\
+            \x20 <region id=\"ModuleSettings\">\n\
+            \x20   <node id=\"root\">\n\
+            \x20     <children>\n\
+            \x20       <node id=\"Mods\">\n\
+            \x20         <children>\n\
+            {}\
+            \x20         </children>\n\
+            \x20       </node>\n\
+            \x20     </children>\n\
+            \x20   </node>\n\
+            \x20 </region>\n\
+            </save>\n",
+            modules
+        )
+    }
+}
+
+// Add new writers here
+pub fn writer_for(format: &str) -> Option<Box<dyn LoadOrderWriter>> {
+    let writers: Vec<Box<dyn LoadOrderWriter>> = vec![
+        Box::new(PluginsTxtWriter),
+        Box::new(PlainListWriter),
+        Box::new(ModSettingsLsxWriter),
+    ];
+    writers.into_iter().find(|w| w.format() == format)
+}
+
+pub fn write_load_order(
+    writer: &dyn LoadOrderWriter,
+    entries: &[WriterEntry],
+    target: &Path,
+) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(target, writer.render(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mods::loadorder::tests::order;
+
+    fn manifest(id: &str, name: Option<&str>, extra: &[(&str, &str)]) -> ModManifest {
+        ModManifest {
+            id: id.to_string(),
+            name: name.map(|n| n.to_string()),
+            extra: extra
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn render(format: &str, entries: &[LoadOrderEntry], manifests: &[ModManifest]) -> String {
+        let entries: Vec<WriterEntry> = entries
+            .iter()
+            .map(|entry| WriterEntry {
+                entry,
+                manifest: manifests.iter().find(|m| m.id == entry.mod_id),
+            })
+            .collect();
+        writer_for(format).unwrap().render(&entries)
+    }
+
+    #[test]
+    fn plugins_txt_marks_enabled_plugins() {
+        let mut order = order(&["base", "patch", "other"]);
+        order.entries[1].enabled = false;
+        let manifests = vec![manifest("base", None, &[("plugin", "Base.esp")])];
+        assert_eq!(
+            render("plugins.txt", &order.entries, &manifests),
+            "# This file is generated by Mud\n*Base.esp\npatch\n*other\n"
+        );
+    }
+
+    #[test]
+    fn list_has_only_enabled_mods() {
+        let mut order = order(&["a", "b", "c"]);
+        order.entries[0].enabled = false;
+        let manifests = vec![manifest("c", None, &[("file", "c.pak")])];
+        assert_eq!(render("list", &order.entries, &manifests), "b\nc.pak\n");
+    }
+
+    #[test]
+    fn modsettings_lists_the_base_module_then_enabled_pak_mods() {
+        let mut order = order(&["pak", "script", "off"]);
+        order.entries[2].enabled = false;
+        let manifests = vec![
+            manifest(
+                "pak",
+                Some("Pak & <Friends>"),
+                &[("uuid", "1234"), ("folder", "PakFolder")],
+            ),
+            manifest("off", None, &[("uuid", "5678")]),
+        ];
+        let lsx = render("modsettings.lsx", &order.entries, &manifests);
+
+        assert!(lsx.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<save>\n"));
+        assert!(lsx.ends_with("</save>\n"));
+        let modules: Vec<&str> = lsx
+            .lines()
+            .filter(|line| line.contains("id=\"UUID\""))
+            .collect();
+        assert_eq!(modules.len(), 2);
+        assert!(modules[0].contains(BG3_BASE_MODULE.1));
+        assert!(modules[1].contains("value=\"1234\""));
+        assert!(lsx.contains("value=\"PakFolder\""));
+        assert!(lsx.contains("value=\"Pak &amp; &lt;Friends&gt;\""));
+        assert!(!lsx.contains("5678"));
+    }
+
+    #[test]
+    fn unknown_formats_have_no_writer() {
+        assert!(writer_for("plugins.txt").is_some());
+        assert!(writer_for("loadorder.txt").is_none());
+    }
+}
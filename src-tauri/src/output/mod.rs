@@ -1,3 +1,5 @@
 pub mod capture;
+pub mod plan;
 
 pub use capture::*;
+pub use plan::*;
@@ -0,0 +1,82 @@
+use crate::context::includes::LineMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub command: String,
+    pub line: usize,
+    // Set when the script was read from a file, or the line came from an included one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedDownload {
+    pub url: String,
+    // Only known for `download`, which requires one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub destination: Option<PathBuf>,
+    pub command: String,
+    pub line: usize,
+    // Set when the script was read from a file, or the line came from an included one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedProcess {
+    pub program: String,
+    pub args: Vec<String>,
+    pub command: String,
+    pub line: usize,
+    // Set when the script was read from a file, or the line came from an included one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<PathBuf>,
+    // The exit code the dry run reported back to the script, so branches on it may differ from a real run
+    #[serde(default)]
+    pub assumed_exit_code: i32,
+}
+
+// Everything a dry run would have done, in the order the script asked for it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScriptPlan {
+    pub writes: Vec<PlannedFile>,
+    pub deletes: Vec<PlannedFile>,
+    pub downloads: Vec<PlannedDownload>,
+    pub processes: Vec<PlannedProcess>,
+}
+
+impl ScriptPlan {
+    // Points each entry at the file and line it came from before includes were expanded
+    pub fn map_lines(mut self, lines: &LineMap) -> Self {
+        let files = self.writes.iter_mut().chain(self.deletes.iter_mut());
+        for planned in files {
+            if let Some(source) = lines.source(planned.line) {
+                planned.line = source.line;
+                planned.source_file = source.file.clone();
+            }
+        }
+        for download in &mut self.downloads {
+            if let Some(source) = lines.source(download.line) {
+                download.line = source.line;
+                download.source_file = source.file.clone();
+            }
+        }
+        for process in &mut self.processes {
+            if let Some(source) = lines.source(process.line) {
+                process.line = source.line;
+                process.source_file = source.file.clone();
+            }
+        }
+        self
+    }
+}
+
+pub type SharedPlan = Arc<Mutex<ScriptPlan>>;
@@ -0,0 +1,397 @@
+use crate::utils::files::walk_files;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "zip" => Some(Self::Zip),
+            "tar" => Some(Self::Tar),
+            "tar.gz" | "tgz" | "gz" => Some(Self::TarGz),
+            "tar.xz" | "txz" | "xz" => Some(Self::TarXz),
+            "7z" => Some(Self::SevenZ),
+            _ => None,
+        }
+    }
+
+    // Guesses the format from the file name
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        [".tar.gz", ".tar.xz", ".tgz", ".txz", ".tar", ".zip", ".7z"]
+            .iter()
+            .find(|ext| name.ends_with(*ext))
+            .and_then(|ext| Self::from_name(ext.trim_start_matches('.')))
+    }
+}
+
+/*
+Which entries to extract. `sub_path` picks a folder inside the archive and
+strips it from the output paths, globs match against the stripped path.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    pub sub_path: Option<PathBuf>,
+    pub include: Option<GlobSet>,
+    pub exclude: Option<GlobSet>,
+}
+
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| e.to_string())?);
+    }
+    builder.build().map(Some).map_err(|e| e.to_string())
+}
+
+impl ExtractOptions {
+    pub fn new(
+        sub_path: Option<String>,
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<Self, String> {
+        Ok(Self {
+            sub_path: sub_path.map(|p| PathBuf::from(p.trim_matches('/'))),
+            include: glob_set(include)?,
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    // Output path for an entry relative to the destination, None when it's filtered out
+    fn target(&self, name: &str) -> io::Result<Option<PathBuf>> {
+        let relative = safe_entry_path(name)?;
+        let relative = match &self.sub_path {
+            Some(sub_path) => match relative.strip_prefix(sub_path) {
+                Ok(stripped) if !stripped.as_os_str().is_empty() => stripped.to_path_buf(),
+                _ => return Ok(None),
+            },
+            None => relative,
+        };
+        if relative.as_os_str().is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(include) = &self.include {
+            if !include.is_match(&relative) {
+                return Ok(None);
+            }
+        }
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(&relative) {
+                return Ok(None);
+            }
+        }
+        Ok(Some(relative))
+    }
+}
+
+// Rejects absolute paths and `..` so entries can't land outside the destination
+fn safe_entry_path(name: &str) -> io::Result<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(&name.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("archive entry '{}' points outside the destination", name),
+                ));
+            }
+        }
+    }
+    Ok(path)
+}
+
+fn write_entry(reader: &mut dyn Read, destination: &Path, relative: &Path) -> io::Result<PathBuf> {
+    let target = destination.join(relative);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(&target)?;
+    io::copy(reader, &mut file)?;
+    Ok(target)
+}
+
+fn open_tar(path: &Path, format: ArchiveFormat) -> io::Result<tar::Archive<Box<dyn Read>>> {
+    let file = BufReader::new(File::open(path)?);
+    let reader: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(file)),
+        ArchiveFormat::TarXz => Box::new(xz2::read::XzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+/*
+Calls `visit` with the name and contents of every regular file in an archive.
+Links and special files are skipped. Return false from `visit` to stop early.
+*/
+fn for_each_file<F>(path: &Path, format: ArchiveFormat, mut visit: F) -> io::Result<()>
+where
+    F: FnMut(&str, &mut dyn Read) -> io::Result<bool>,
+{
+    match format {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))
+                .map_err(io::Error::other)?;
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index).map_err(io::Error::other)?;
+                if entry.is_dir() || entry.is_symlink() {
+                    continue;
+                }
+                let name = entry.name().to_string();
+                if !visit(&name, &mut entry)? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarXz => {
+            let mut archive = open_tar(path, format)?;
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path()?.to_string_lossy().to_string();
+                if !visit(&name, &mut entry)? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        ArchiveFormat::SevenZ => {
+            let mut failure = None;
+            let mut archive = sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty())
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            archive
+                .for_each_entries(|entry, reader| {
+                    if entry.is_directory() {
+                        return Ok(true);
+                    }
+                    match visit(entry.name(), reader) {
+                        Ok(keep_going) => Ok(keep_going),
+                        Err(e) => {
+                            failure = Some(e);
+                            Ok(false)
+                        }
+                    }
+                })
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            failure.map_or(Ok(()), Err)
+        }
+    }
+}
+
+// Names of the files in an archive, in archive order
+pub fn list_archive(path: &Path, format: ArchiveFormat) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for_each_file(path, format, |name, reader| {
+        names.push(name.to_string());
+        io::copy(reader, &mut io::sink())?;
+        Ok(true)
+    })?;
+    Ok(names)
+}
+
+// Where `extract_archive` would write each selected file, without extracting anything
+pub fn extract_targets(
+    path: &Path,
+    destination: &Path,
+    format: ArchiveFormat,
+    options: &ExtractOptions,
+) -> io::Result<Vec<PathBuf>> {
+    let mut targets = Vec::new();
+    for name in list_archive(path, format)? {
+        if let Some(relative) = options.target(&name)? {
+            targets.push(destination.join(relative));
+        }
+    }
+    Ok(targets)
+}
+
+// Extracts the selected files into `destination`, returning where each one was written
+pub fn extract_archive(
+    path: &Path,
+    destination: &Path,
+    format: ArchiveFormat,
+    options: &ExtractOptions,
+) -> io::Result<Vec<PathBuf>> {
+    let mut extracted = Vec::new();
+    for_each_file(path, format, |name, reader| {
+        match options.target(name)? {
+            Some(relative) => extracted.push(write_entry(reader, destination, &relative)?),
+            // Solid 7z blocks are read in order, so skipped entries still get drained
+            None => {
+                io::copy(reader, &mut io::sink())?;
+            }
+        }
+        Ok(true)
+    })?;
+    Ok(extracted)
+}
+
+// Zips every file under `source`, with paths relative to it
+pub fn create_zip(source: &Path, archive: &Path) -> io::Result<()> {
+    if let Some(parent) = archive.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut writer = zip::ZipWriter::new(File::create(archive)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for path in walk_files(source) {
+        let relative = path.strip_prefix(source).map_err(io::Error::other)?;
+        let name = relative.to_string_lossy().replace('\\', "/");
+        writer.start_file(name, options).map_err(io::Error::other)?;
+        io::copy(&mut File::open(&path)?, &mut writer)?;
+    }
+    writer.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::temp_dir;
+    use std::io::Write;
+
+    fn options(sub_path: Option<&str>, include: &[&str], exclude: &[&str]) -> ExtractOptions {
+        let globs = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        ExtractOptions::new(sub_path.map(String::from), &globs(include), &globs(exclude)).unwrap()
+    }
+
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in entries {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn safe_entry_path_keeps_relative_paths() {
+        assert_eq!(
+            safe_entry_path("a/b.txt").unwrap(),
+            PathBuf::from("a/b.txt")
+        );
+        assert_eq!(
+            safe_entry_path("./a/./b.txt").unwrap(),
+            PathBuf::from("a/b.txt")
+        );
+        assert_eq!(
+            safe_entry_path("a\\b.txt").unwrap(),
+            PathBuf::from("a/b.txt")
+        );
+    }
+
+    #[test]
+    fn safe_entry_path_rejects_zip_slip() {
+        for name in [
+            "../evil.txt",
+            "a/../../evil.txt",
+            "..\\evil.txt",
+            "/etc/passwd",
+        ] {
+            let error = safe_entry_path(name).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", name);
+        }
+    }
+
+    #[test]
+    fn sub_path_is_stripped_and_limits_entries() {
+        let options = options(Some("/Data/"), &[], &[]);
+        assert_eq!(
+            options.target("Data/mods/a.pak").unwrap(),
+            Some(PathBuf::from("mods/a.pak"))
+        );
+        assert_eq!(options.target("Data").unwrap(), None);
+        assert_eq!(options.target("DataExtra/a.pak").unwrap(), None);
+        assert_eq!(options.target("readme.txt").unwrap(), None);
+    }
+
+    #[test]
+    fn globs_match_the_stripped_path() {
+        let options = options(Some("Data"), &["*.pak", "mods/**"], &["**/skip.pak"]);
+        assert_eq!(
+            options.target("Data/a.pak").unwrap(),
+            Some(PathBuf::from("a.pak"))
+        );
+        assert_eq!(
+            options.target("Data/mods/readme.md").unwrap(),
+            Some(PathBuf::from("mods/readme.md"))
+        );
+        assert_eq!(options.target("Data/mods/skip.pak").unwrap(), None);
+        assert_eq!(options.target("Data/notes.txt").unwrap(), None);
+        // Matched after stripping, so the sub path itself isn't part of the glob
+        assert_eq!(options.target("Data/Data/a.txt").unwrap(), None);
+    }
+
+    #[test]
+    fn extract_writes_only_selected_files() {
+        let dir = temp_dir("extract");
+        let archive = dir.join("mod.zip");
+        write_zip(
+            &archive,
+            &[
+                ("Data/a.pak", "a"),
+                ("Data/skip.pak", "skip"),
+                ("Data/readme.txt", "readme"),
+                ("other.pak", "other"),
+            ],
+        );
+        let destination = dir.join("out");
+        let options = options(Some("Data"), &["*.pak"], &["skip.pak"]);
+
+        let planned =
+            extract_targets(&archive, &destination, ArchiveFormat::Zip, &options).unwrap();
+        let extracted =
+            extract_archive(&archive, &destination, ArchiveFormat::Zip, &options).unwrap();
+        assert_eq!(extracted, vec![destination.join("a.pak")]);
+        assert_eq!(planned, extracted);
+        assert_eq!(
+            std::fs::read_to_string(destination.join("a.pak")).unwrap(),
+            "a"
+        );
+        assert!(!destination.join("skip.pak").exists());
+        assert!(!destination.join("readme.txt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_refuses_entries_outside_the_destination() {
+        let dir = temp_dir("slip");
+        let archive = dir.join("evil.zip");
+        write_zip(&archive, &[("../evil.txt", "evil")]);
+        let destination = dir.join("out");
+
+        let error = extract_archive(
+            &archive,
+            &destination,
+            ArchiveFormat::Zip,
+            &options(None, &[], &[]),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!dir.join("evil.txt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
+use crate::context::includes::LineMap;
 use duckscript::types::error::ScriptError;
 use serde::Serialize;
+use std::path::PathBuf;
 use tauri::Error as TauriError;
 
 #[derive(Serialize)]
@@ -8,6 +10,9 @@ pub struct ScriptErrorResponse {
     stderr: String,
     message: String,
     line: Option<usize>,
+    // File the line is in, an included file's when it came from `!include_files`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_file: Option<PathBuf>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -20,6 +25,20 @@ pub enum AppError {
     StoreError(#[from] tauri_plugin_store::Error),
     #[error("Parsing Error: {0}")]
     ParsingError(String),
+    #[error("Profile Error: {0}")]
+    ProfileError(String),
+    #[error("Install Error: {0}")]
+    InstallError(String),
+    #[error("Dependency Error: {0}")]
+    ResolveError(String),
+    #[error("Load Order Error: {0}")]
+    LoadOrderError(String),
+    #[error("Download Error: {0}")]
+    DownloadError(String),
+    #[error("Backup Error: {0}")]
+    BackupError(String),
+    #[error("Launch Error: {0}")]
+    LaunchError(String),
     #[error("Tauri Error: {0}")]
     Tauri(#[from] TauriError),
 }
@@ -41,6 +60,7 @@ pub fn handle_script_error(
     error: ScriptError,
     stderr_output: String,
     stdout_output: String,
+    lines: &LineMap,
 ) -> String {
     // Determine the line number and construct the error message based on the error type
     let (error_message, line_number) = match error {
@@ -80,10 +100,16 @@ pub fn handle_script_error(
         _ => (format!("Unknown error occurred"), None),
     };
 
+    // Point back at the file and line before includes were expanded
+    let source = line_number.and_then(|line| lines.source(line));
+    let source_file = source.and_then(|source| source.file.clone());
+    let line_number = source.map(|source| source.line).or(line_number);
+
     // Construct error json
     let script_error_response = ScriptErrorResponse {
         message: error_message,
         line: line_number,
+        source_file,
         stderr: stderr_output,
         stdout: stdout_output,
     };
@@ -0,0 +1,70 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+// Every regular file under a path, or the path itself if it is a file
+pub fn walk_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+// Resolves a script supplied path against the current working directory
+pub fn absolute_path(path: &Path) -> io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(normalize_path(path))
+    } else {
+        Ok(normalize_path(&std::env::current_dir()?.join(path)))
+    }
+}
+
+// Lexically removes `.` and `..` components without touching the filesystem
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+// Copies a file or a whole directory tree
+pub fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        for file in walk_files(from) {
+            if let Ok(relative) = file.strip_prefix(from) {
+                copy_file(&file, &to.join(relative))?;
+            }
+        }
+        Ok(())
+    } else {
+        copy_file(from, to).map(|_| ())
+    }
+}
+
+// Removes a file or a whole directory tree, ignoring paths that don't exist
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else if path.exists() {
+        std::fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+// Copies a file, creating any missing parent directories first
+pub fn copy_file(from: &Path, to: &Path) -> io::Result<u64> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(from, to)
+}
@@ -1,4 +1,9 @@
+pub mod archive;
 pub mod error_handler;
+pub mod files;
+pub mod hashing;
+#[cfg(test)]
+pub mod testing;
 // pub mod tasks;
 
 pub use error_handler::*;
@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Keeps folders apart when tests run in parallel or a run was aborted
+static NEXT_TEMP_DIR: AtomicUsize = AtomicUsize::new(0);
+
+// A fresh folder under the system temp dir, removed by the caller
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "mud-test-{}-{}-{}",
+        name,
+        std::process::id(),
+        NEXT_TEMP_DIR.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}